@@ -0,0 +1,54 @@
+use std::fmt::{Display, Formatter};
+
+/// A zero-width assertion about the position between two characters.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Assertion {
+    /// `^` in multi-line mode: start of the text or right after a `\n`.
+    StartLine,
+    /// `$` in multi-line mode: end of the text or right before a `\n`.
+    EndLine,
+    /// `\A`, and `^` outside multi-line mode.
+    StartText,
+    /// `\z`, and `$` outside multi-line mode.
+    EndText,
+    /// `\b` with ASCII word characters.
+    AsciiWordBoundary,
+    /// `\B` with ASCII word characters.
+    AsciiNotWordBoundary,
+    /// `\b` with Unicode word characters.
+    UnicodeWordBoundary,
+    /// `\B` with Unicode word characters.
+    UnicodeNotWordBoundary,
+}
+
+impl Assertion {
+    /// Whether the assertion holds between `prev` and `next`, `None` standing
+    /// for either end of the text.
+    pub fn holds(&self, prev: Option<char>, next: Option<char>) -> bool {
+        match self {
+            Self::StartLine => prev.is_none() || prev == Some('\n'),
+            Self::EndLine => next.is_none() || next == Some('\n'),
+            Self::StartText => prev.is_none(),
+            Self::EndText => next.is_none(),
+            Self::AsciiWordBoundary => is_word(prev, false) != is_word(next, false),
+            Self::AsciiNotWordBoundary => is_word(prev, false) == is_word(next, false),
+            Self::UnicodeWordBoundary => is_word(prev, true) != is_word(next, true),
+            Self::UnicodeNotWordBoundary => is_word(prev, true) == is_word(next, true),
+        }
+    }
+}
+
+/// Whether `c` is a word character, as in `\w`. The ends of the text are not.
+fn is_word(c: Option<char>, unicode: bool) -> bool {
+    match c {
+        Some(c) if unicode => c.is_alphanumeric() || c == '_',
+        Some(c) => c.is_ascii_alphanumeric() || c == '_',
+        None => false,
+    }
+}
+
+impl Display for Assertion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
@@ -0,0 +1,211 @@
+//! The abstract syntax tree of a pattern, shared by every front end. The parser
+//! of `regex1` produces it, see `regex1::parser::Parser`, and each engine builds
+//! its automaton from it.
+//!
+//! Flags are already applied: case-insensitive literals come as classes of
+//! their cases, and `.`, shorthands, `^`, `$`, `\b` and `\B` as the classes and
+//! assertions they stand for under the flags in effect where they are written.
+
+use std::ops::Range;
+
+use crate::assertion::Assertion;
+use crate::class::CharClass;
+use crate::error::{Error, ErrorKind};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ast {
+    pub kind: AstKind,
+    /// Where the node is in the pattern, in chars like the spans of `Error`.
+    pub span: Range<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AstKind {
+    /// The empty string: an empty pattern, alternative or group, or `(?flags)`.
+    Empty,
+    Literal(char),
+    /// A normalized class, with any negation and set operation applied.
+    Class(CharClass),
+    Assertion(Assertion),
+    /// At least two nodes, one after the other.
+    Concat(Vec<Ast>),
+    /// At least two alternatives, the first preferred.
+    Alternation(Vec<Ast>),
+    /// At least two nodes that must all match, written with `&` in `regex0`.
+    Intersection(Vec<Ast>),
+    /// What the node does not match, written with `~` in `regex0`.
+    Complement(Box<Ast>),
+    Repetition(Repetition),
+    Group(Group),
+    /// The text last captured by a group, matched again.
+    Backreference {
+        index: usize,
+        case_insensitive: bool,
+    },
+    Conditional(Conditional),
+    /// A call to the group of this index, 0 standing for the whole pattern.
+    Call(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Repetition {
+    pub min: usize,
+    /// `None` for no upper bound.
+    pub max: Option<usize>,
+    pub greed: Greed,
+    pub ast: Box<Ast>,
+}
+
+/// How a repetition picks among the counts it allows.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Greed {
+    /// As many as possible first, then fewer.
+    Greedy,
+    /// As few as possible first, then more.
+    Lazy,
+    /// As many as possible, never giving any back.
+    Possessive,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Group {
+    pub kind: GroupKind,
+    pub ast: Box<Ast>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GroupKind {
+    /// A capture group, with its index.
+    Capture(usize),
+    NonCapture,
+    /// Commits to the first match of its contents.
+    Atomic,
+    /// A lookahead or, if not `ahead`, a lookbehind. It holds where its
+    /// contents match, or where they do not if `negated`.
+    Look {
+        ahead: bool,
+        negated: bool,
+    },
+    /// Matches the empty string, its groups only being there to be called.
+    Define,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conditional {
+    pub condition: Condition,
+    /// Taken when the condition holds.
+    pub yes: Box<Ast>,
+    /// Taken otherwise. A missing one matches the empty string.
+    pub no: Option<Box<Ast>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Condition {
+    /// Whether the group of this index participated in the match so far.
+    Group(usize),
+    /// A `Group` node of kind `GroupKind::Look`.
+    Look(Box<Ast>),
+}
+
+impl Ast {
+    pub fn new(kind: AstKind, span: Range<usize>) -> Ast {
+        Ast { kind, span }
+    }
+
+    /// The error for this node in `pattern`, when an engine cannot match it.
+    pub fn unsupported<S: Into<String>>(&self, pattern: &str, reason: S) -> Error {
+        Error::new(
+            ErrorKind::Unsupported(reason.into()),
+            pattern,
+            self.span.clone(),
+        )
+    }
+
+    /// The nodes right under this one, in the order they are written.
+    pub fn children(&self) -> Vec<&Ast> {
+        match &self.kind {
+            AstKind::Concat(asts) | AstKind::Alternation(asts) | AstKind::Intersection(asts) => {
+                asts.iter().collect()
+            }
+            AstKind::Complement(ast) => vec![ast],
+            AstKind::Repetition(repetition) => vec![&repetition.ast],
+            AstKind::Group(group) => vec![&group.ast],
+            AstKind::Conditional(conditional) => {
+                let mut children = vec![];
+                if let Condition::Look(look) = &conditional.condition {
+                    children.push(look.as_ref());
+                }
+                children.push(&conditional.yes);
+                children.extend(conditional.no.as_deref());
+                children
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Like `children`, for changing them.
+    pub fn children_mut(&mut self) -> Vec<&mut Ast> {
+        match &mut self.kind {
+            AstKind::Concat(asts) | AstKind::Alternation(asts) | AstKind::Intersection(asts) => {
+                asts.iter_mut().collect()
+            }
+            AstKind::Complement(ast) => vec![ast],
+            AstKind::Repetition(repetition) => vec![&mut repetition.ast],
+            AstKind::Group(group) => vec![&mut group.ast],
+            AstKind::Conditional(conditional) => {
+                let mut children = vec![];
+                if let Condition::Look(look) = &mut conditional.condition {
+                    children.push(look.as_mut());
+                }
+                children.push(&mut conditional.yes);
+                children.extend(conditional.no.as_deref_mut());
+                children
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Builds a value of the tree from the leaves up: `build` is given each
+    /// node with the values built for its children, in order. The nodes are
+    /// visited with an explicit stack, so a deep tree takes no stack frame
+    /// per level. The child of a repetition is built as many times as
+    /// `copies` says, for automata taking a fresh copy of it per repetition.
+    pub fn fold<T, E, C, B>(&self, mut copies: C, mut build: B) -> Result<T, E>
+    where
+        C: FnMut(&Repetition) -> usize,
+        B: FnMut(&Ast, Vec<T>) -> Result<T, E>,
+    {
+        let mut built: Vec<T> = vec![];
+        // Each node is visited twice: to queue its children, then to build it
+        // from as many values as it has children.
+        let mut pending = vec![(self, None)];
+        while let Some((ast, children)) = pending.pop() {
+            if let Some(children) = children {
+                let operands = built.split_off(built.len() - children);
+                built.push(build(ast, operands)?);
+                continue;
+            }
+
+            let children = match &ast.kind {
+                AstKind::Repetition(repetition) => vec![&*repetition.ast; copies(repetition)],
+                _ => ast.children(),
+            };
+            pending.push((ast, Some(children.len())));
+            pending.extend(children.into_iter().rev().map(|child| (child, None)));
+        }
+
+        Ok(built.pop().unwrap())
+    }
+}
+
+// Dropping a deep tree recursively would take one stack frame per level, so
+// the nodes under this one are taken out and dropped in a loop instead.
+impl Drop for Ast {
+    fn drop(&mut self) {
+        let take = |child: &mut Ast| std::mem::replace(child, Ast::new(AstKind::Empty, 0..0));
+        let mut pending: Vec<Ast> = self.children_mut().into_iter().map(take).collect();
+        while let Some(mut ast) = pending.pop() {
+            pending.extend(ast.children_mut().into_iter().map(take));
+        }
+    }
+}
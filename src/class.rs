@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::OnceLock;
+
+use crate::tables;
+
+/// A set of characters, kept as a sorted list of non-overlapping, non-adjacent
+/// inclusive ranges. Every operation returns the set in this canonical form so
+/// two classes containing the same characters always compare equal.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CharClass {
+    ranges: Vec<(char, char)>,
+}
+
+impl CharClass {
+    pub fn new() -> CharClass {
+        CharClass { ranges: vec![] }
+    }
+
+    pub fn from_char(c: char) -> CharClass {
+        Self::from_range(c, c)
+    }
+
+    pub fn from_range(start: char, end: char) -> CharClass {
+        Self::from_ranges(vec![(start, end)])
+    }
+
+    pub fn from_ranges<I: IntoIterator<Item = (char, char)>>(ranges: I) -> CharClass {
+        let mut class = CharClass {
+            ranges: ranges.into_iter().filter(|(s, e)| s <= e).collect(),
+        };
+        class.canonicalize();
+        class
+    }
+
+    pub fn any() -> CharClass {
+        Self::from_range('\0', char::MAX)
+    }
+
+    /// Builds the class of every `char` satisfying `predicate`. This walks the
+    /// whole code space, so callers should cache the result.
+    pub fn from_predicate<F: Fn(char) -> bool>(predicate: F) -> CharClass {
+        let mut ranges: Vec<(char, char)> = vec![];
+        for c in ('\0'..=char::MAX).filter(|&c| predicate(c)) {
+            match ranges.last_mut() {
+                Some(last) if next_char(last.1) == Some(c) => last.1 = c,
+                _ => ranges.push((c, c)),
+            }
+        }
+        CharClass { ranges }
+    }
+
+    /// `\d`: ASCII digits, or every decimal digit, the `Nd` general category,
+    /// when `unicode` is set.
+    pub fn digit(unicode: bool) -> CharClass {
+        if unicode {
+            Self::from_ranges(tables::DECIMAL_NUMBER.iter().copied())
+        } else {
+            Self::from_range('0', '9')
+        }
+    }
+
+    /// `\w`: ASCII letters, digits and `_`, or every alphanumeric `char` and `_`
+    /// when `unicode` is set.
+    pub fn word(unicode: bool) -> CharClass {
+        static UNICODE: OnceLock<CharClass> = OnceLock::new();
+        if unicode {
+            UNICODE
+                .get_or_init(|| Self::from_predicate(|c| c.is_alphanumeric() || c == '_'))
+                .clone()
+        } else {
+            Self::from_ranges(vec![('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')])
+        }
+    }
+
+    /// `\s`: ASCII whitespace including vertical tab, or every whitespace `char`
+    /// when `unicode` is set.
+    pub fn space(unicode: bool) -> CharClass {
+        static UNICODE: OnceLock<CharClass> = OnceLock::new();
+        if unicode {
+            UNICODE
+                .get_or_init(|| Self::from_predicate(char::is_whitespace))
+                .clone()
+        } else {
+            Self::from_ranges(vec![('\t', '\r'), (' ', ' ')])
+        }
+    }
+
+    /// `\p{name}`: the class of a Unicode property. Names are matched
+    /// case-insensitively. The letter categories and `Nd` come from the tables
+    /// of `tables`, the other properties from the matching classification
+    /// method on `char`.
+    pub fn property(name: &str) -> Option<CharClass> {
+        static NUMBER: OnceLock<CharClass> = OnceLock::new();
+        static UPPERCASE: OnceLock<CharClass> = OnceLock::new();
+        static LOWERCASE: OnceLock<CharClass> = OnceLock::new();
+        static WHITESPACE: OnceLock<CharClass> = OnceLock::new();
+        static ALPHABETIC: OnceLock<CharClass> = OnceLock::new();
+        static CONTROL: OnceLock<CharClass> = OnceLock::new();
+
+        let name = name.to_ascii_lowercase();
+        let letters: Option<&[&[(char, char)]]> = match name.as_str() {
+            "l" | "letter" => Some(&[
+                tables::UPPERCASE_LETTER,
+                tables::LOWERCASE_LETTER,
+                tables::TITLECASE_LETTER,
+                tables::MODIFIER_LETTER,
+                tables::OTHER_LETTER,
+            ]),
+            "lu" | "uppercase_letter" => Some(&[tables::UPPERCASE_LETTER]),
+            "ll" | "lowercase_letter" => Some(&[tables::LOWERCASE_LETTER]),
+            "lt" | "titlecase_letter" => Some(&[tables::TITLECASE_LETTER]),
+            "lm" | "modifier_letter" => Some(&[tables::MODIFIER_LETTER]),
+            "lo" | "other_letter" => Some(&[tables::OTHER_LETTER]),
+            _ => None,
+        };
+        if let Some(tables) = letters {
+            return Some(Self::from_ranges(tables.concat()));
+        }
+        if let "nd" | "decimal_number" = name.as_str() {
+            return Some(Self::digit(true));
+        }
+
+        let (cell, predicate): (&OnceLock<CharClass>, fn(char) -> bool) = match name.as_str() {
+            "n" | "number" => (&NUMBER, char::is_numeric),
+            "upper" | "uppercase" => (&UPPERCASE, char::is_uppercase),
+            "lower" | "lowercase" => (&LOWERCASE, char::is_lowercase),
+            "whitespace" | "white_space" => (&WHITESPACE, char::is_whitespace),
+            "alpha" | "alphabetic" => (&ALPHABETIC, char::is_alphabetic),
+            "cc" | "control" => (&CONTROL, char::is_control),
+            _ => return None,
+        };
+
+        Some(cell.get_or_init(|| Self::from_predicate(predicate)).clone())
+    }
+
+    /// `[:name:]`: the ASCII POSIX bracket classes.
+    pub fn posix(name: &str) -> Option<CharClass> {
+        let ranges: &[(char, char)] = match name {
+            "alnum" => &[('0', '9'), ('A', 'Z'), ('a', 'z')],
+            "alpha" => &[('A', 'Z'), ('a', 'z')],
+            "ascii" => &[('\0', '\x7F')],
+            "blank" => &[('\t', '\t'), (' ', ' ')],
+            "cntrl" => &[('\0', '\x1F'), ('\x7F', '\x7F')],
+            "digit" => &[('0', '9')],
+            "graph" => &[('!', '~')],
+            "lower" => &[('a', 'z')],
+            "print" => &[(' ', '~')],
+            "punct" => &[('!', '/'), (':', '@'), ('[', '`'), ('{', '~')],
+            "space" => &[('\t', '\r'), (' ', ' ')],
+            "upper" => &[('A', 'Z')],
+            "word" => &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')],
+            "xdigit" => &[('0', '9'), ('A', 'F'), ('a', 'f')],
+            _ => return None,
+        };
+
+        Some(Self::from_ranges(ranges.iter().copied()))
+    }
+
+    pub fn ranges(&self) -> &[(char, char)] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.ranges
+            .binary_search_by(|&(s, e)| {
+                if e < c {
+                    std::cmp::Ordering::Less
+                } else if s > c {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .is_ok()
+    }
+
+    pub fn push(&mut self, start: char, end: char) {
+        self.ranges.push((start, end));
+        self.canonicalize();
+    }
+
+    pub fn union(&self, other: &CharClass) -> CharClass {
+        Self::from_ranges(self.ranges.iter().chain(other.ranges.iter()).copied())
+    }
+
+    pub fn intersect(&self, other: &CharClass) -> CharClass {
+        let mut ranges = vec![];
+        let (mut i, mut j) = (0, 0);
+
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a_start, a_end) = self.ranges[i];
+            let (b_start, b_end) = other.ranges[j];
+
+            let start = a_start.max(b_start);
+            let end = a_end.min(b_end);
+            if start <= end {
+                ranges.push((start, end));
+            }
+
+            if a_end < b_end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        CharClass { ranges }
+    }
+
+    pub fn difference(&self, other: &CharClass) -> CharClass {
+        self.intersect(&other.negate())
+    }
+
+    pub fn symmetric_difference(&self, other: &CharClass) -> CharClass {
+        self.union(other).difference(&self.intersect(other))
+    }
+
+    /// Adds the other cases of every character in the class, see `case_orbits`.
+    /// Only the cased characters in the class are visited.
+    pub fn case_fold(&self) -> CharClass {
+        let orbits = case_orbits();
+        let mut ranges = self.ranges.clone();
+        for &(start, end) in &self.ranges {
+            let first = orbits.partition_point(|&(c, _)| c < start);
+            for &(_, other) in orbits[first..].iter().take_while(|&&(c, _)| c <= end) {
+                ranges.push((other, other));
+            }
+        }
+
+        Self::from_ranges(ranges)
+    }
+
+    pub fn negate(&self) -> CharClass {
+        let mut ranges = Vec::with_capacity(self.ranges.len() + 1);
+        let mut next = Some('\0');
+
+        for &(s, e) in &self.ranges {
+            if let Some(n) = next {
+                if n < s {
+                    ranges.push((n, prev_char(s).unwrap()));
+                }
+            }
+            next = next_char(e);
+        }
+
+        if let Some(n) = next {
+            ranges.push((n, char::MAX));
+        }
+
+        CharClass { ranges }
+    }
+
+    fn canonicalize(&mut self) {
+        self.ranges.sort_unstable();
+
+        let mut merged: Vec<(char, char)> = Vec::with_capacity(self.ranges.len());
+        for &(s, e) in &self.ranges {
+            if let Some(last) = merged.last_mut() {
+                if next_char(last.1).is_none_or(|n| s <= n) {
+                    if e > last.1 {
+                        last.1 = e;
+                    }
+                    continue;
+                }
+            }
+            merged.push((s, e));
+        }
+
+        self.ranges = merged;
+    }
+}
+
+impl Display for CharClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[")?;
+        for &(s, e) in &self.ranges {
+            if s == e {
+                write!(f, "{}", s.escape_debug())?;
+            } else {
+                write!(f, "{}-{}", s.escape_debug(), e.escape_debug())?;
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+/// Every character with other cases, paired with each of them, sorted. The
+/// characters matching one another case-insensitively are those linked by the
+/// simple one-to-one mappings of `char::to_lowercase` and `char::to_uppercase`,
+/// directly or not: `k` and `K` both match the Kelvin sign, which only maps to
+/// `k`.
+fn case_orbits() -> &'static [(char, char)] {
+    static ORBITS: OnceLock<Vec<(char, char)>> = OnceLock::new();
+    ORBITS.get_or_init(|| {
+        fn single<I: ExactSizeIterator<Item = char>>(mut chars: I) -> Option<char> {
+            if chars.len() == 1 {
+                chars.next()
+            } else {
+                None
+            }
+        }
+
+        // Characters linked by a mapping are merged into one orbit, found from
+        // any of its members through `parent`.
+        fn root(parent: &HashMap<char, char>, mut c: char) -> char {
+            while let Some(&p) = parent.get(&c) {
+                c = p;
+            }
+            c
+        }
+
+        // No character past the first two planes has a case mapping, the
+        // others being ideographs, tags and private use characters.
+        let mut parent: HashMap<char, char> = HashMap::new();
+        for c in '\0'..='\u{1FFFF}' {
+            for other in [single(c.to_lowercase()), single(c.to_uppercase())] {
+                match other {
+                    Some(other) if other != c => {
+                        let (a, b) = (root(&parent, c), root(&parent, other));
+                        if a != b {
+                            parent.insert(a, b);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let mut orbits: HashMap<char, Vec<char>> = HashMap::new();
+        let cased: Vec<char> = parent.keys().chain(parent.values()).copied().collect();
+        for c in cased {
+            let orbit = orbits.entry(root(&parent, c)).or_default();
+            if !orbit.contains(&c) {
+                orbit.push(c);
+            }
+        }
+
+        let mut pairs = vec![];
+        for orbit in orbits.values() {
+            for &c in orbit {
+                pairs.extend(orbit.iter().filter(|&&o| o != c).map(|&o| (c, o)));
+            }
+        }
+        pairs.sort_unstable();
+        pairs
+    })
+}
+
+// Surrogate code points are not valid `char`s, step over them.
+
+fn next_char(c: char) -> Option<char> {
+    match c {
+        '\u{D7FF}' => Some('\u{E000}'),
+        char::MAX => None,
+        _ => std::char::from_u32(c as u32 + 1),
+    }
+}
+
+fn prev_char(c: char) -> Option<char> {
+    match c {
+        '\u{E000}' => Some('\u{D7FF}'),
+        '\0' => None,
+        _ => std::char::from_u32(c as u32 - 1),
+    }
+}
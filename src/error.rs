@@ -0,0 +1,231 @@
+//! The error returned when a pattern cannot be compiled.
+
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+
+use crate::syntax::{MAX_EXPANSION, MAX_REPEAT};
+
+/// Why a pattern was rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The pattern ended where more was expected.
+    UnexpectedEnd,
+    /// This character was expected instead of what is there.
+    Expected(char),
+    /// A quantifier or a binary operator without its operand.
+    MissingOperand,
+    UnbalancedParenthesis,
+    /// A counted repetition bound above `MAX_REPEAT`.
+    RepeatTooLarge(usize),
+    /// A repetition expanding to more than `MAX_EXPANSION` atoms.
+    ExpansionTooLarge,
+    /// A counted repetition whose maximum is below its minimum.
+    InvalidRepeatRange(usize, usize),
+    /// Groups or classes nested deeper than the limit, given.
+    NestingTooDeep(usize),
+    TrailingBackslash,
+    UnknownEscape(char),
+    /// A malformed code point escape, with what is wrong with it.
+    InvalidEscape(String),
+    InvalidCodePoint(u32),
+    /// A backreference in a front end without a backtracking engine.
+    BackreferenceUnsupported(char),
+    /// A backreference escape in a class, which only holds characters.
+    BackreferenceInClass(char),
+    UnterminatedClass,
+    EmptyClass,
+    /// A class range whose end is before its start, or is a shorthand.
+    InvalidClassRange,
+    UnterminatedPosixClass,
+    UnknownPosixClass(String),
+    UnterminatedProperty,
+    UnknownProperty(String),
+    UnterminatedGroupName,
+    InvalidGroupName(String),
+    DuplicateGroupName(String),
+    UnknownGroupFlag(char),
+    UndefinedBackreference(String),
+    UndefinedCondition(String),
+    UndefinedCall(String),
+    InvalidCondition,
+    TooManyBranches,
+    DefineAlternatives,
+    UnterminatedComment,
+    AlreadyParsed,
+    /// A valid construct that the chosen engine cannot match, with the reason.
+    Unsupported(String),
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedEnd => write!(f, "unexpected end of pattern"),
+            Self::Expected(c) => write!(f, "expected '{}'", c),
+            Self::MissingOperand => write!(f, "operator missing its operand"),
+            Self::UnbalancedParenthesis => write!(f, "unbalanced parenthesis"),
+            Self::RepeatTooLarge(count) => write!(
+                f,
+                "repetition count {} exceeds the maximum of {}",
+                count, MAX_REPEAT
+            ),
+            Self::ExpansionTooLarge => {
+                write!(f, "repetition expands to more than {} atoms", MAX_EXPANSION)
+            }
+            Self::InvalidRepeatRange(min, max) => {
+                write!(f, "invalid repetition range: {{{},{}}}", min, max)
+            }
+            Self::NestingTooDeep(limit) => {
+                write!(f, "nesting deeper than the limit of {}", limit)
+            }
+            Self::TrailingBackslash => write!(f, "pattern ends with a trailing backslash"),
+            Self::UnknownEscape(c) => write!(f, "unknown escape: \\{}", c),
+            Self::InvalidEscape(reason) => write!(f, "invalid escape: {}", reason),
+            Self::InvalidCodePoint(code) => write!(f, "invalid code point in escape: {:X}", code),
+            Self::BackreferenceUnsupported(c) => write!(
+                f,
+                "backreference \\{}: backreferences need a backtracking engine",
+                c
+            ),
+            Self::BackreferenceInClass(c) => write!(
+                f,
+                "backreference \\{} in a character class, which only holds characters",
+                c
+            ),
+            Self::UnterminatedClass => write!(f, "unterminated character class"),
+            Self::EmptyClass => write!(f, "empty character class"),
+            Self::InvalidClassRange => write!(f, "invalid class range"),
+            Self::UnterminatedPosixClass => write!(f, "unterminated posix class"),
+            Self::UnknownPosixClass(name) => write!(f, "unknown posix class: {}", name),
+            Self::UnterminatedProperty => write!(f, "unterminated unicode property name"),
+            Self::UnknownProperty(name) => write!(f, "unknown unicode property: {}", name),
+            Self::UnterminatedGroupName => write!(f, "unterminated group name"),
+            Self::InvalidGroupName(name) => write!(f, "invalid group name: {}", name),
+            Self::DuplicateGroupName(name) => write!(f, "duplicate group name: {}", name),
+            Self::UnknownGroupFlag(c) => write!(f, "unknown group flag: {}", c),
+            Self::UndefinedBackreference(group) => {
+                write!(f, "backreference to undefined group: {}", group)
+            }
+            Self::UndefinedCondition(group) => write!(f, "condition on undefined group: {}", group),
+            Self::UndefinedCall(group) => write!(f, "call to undefined group: {}", group),
+            Self::InvalidCondition => write!(
+                f,
+                "invalid condition: expected a group number, name or lookaround"
+            ),
+            Self::TooManyBranches => write!(f, "conditional group with more than two branches"),
+            Self::DefineAlternatives => write!(f, "a DEFINE group cannot have alternatives"),
+            Self::UnterminatedComment => write!(f, "unterminated comment"),
+            Self::AlreadyParsed => write!(f, "already parsed"),
+            Self::Unsupported(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// An error in a pattern: its kind, the part of the pattern at fault and
+/// possibly a suggestion on how to fix it.
+///
+/// `Display` shows the kind, then the line of the pattern holding the error
+/// with the faulty part underlined, then the suggestion:
+///
+/// ```text
+/// operator missing its operand
+///     a|+b
+///       ^
+/// help: did you mean to escape `+`? write `\+`
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    kind: ErrorKind,
+    pattern: String,
+    /// In chars, the unit the parsers count in.
+    span: Range<usize>,
+    help: Option<String>,
+}
+
+impl Error {
+    /// `span` is in chars, and is clamped to the pattern.
+    pub fn new(kind: ErrorKind, pattern: &str, span: Range<usize>) -> Error {
+        let len = pattern.chars().count();
+        let start = span.start.min(len);
+        Error {
+            kind,
+            pattern: pattern.to_string(),
+            span: start..span.end.clamp(start, len),
+            help: None,
+        }
+    }
+
+    /// Adds a suggestion on how to fix the error.
+    pub fn with_help<S: Into<String>>(mut self, help: S) -> Error {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// An error about the whole pattern, for when no better place is known.
+    pub fn whole(kind: ErrorKind, pattern: &str) -> Error {
+        Self::new(kind, pattern, 0..usize::MAX)
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// The part of the pattern at fault, in bytes.
+    pub fn span(&self) -> Range<usize> {
+        self.byte_offset(self.span.start)..self.byte_offset(self.span.end)
+    }
+
+    /// The part of the pattern at fault, in chars.
+    pub fn char_span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    pub fn help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+
+    fn byte_offset(&self, chars: usize) -> usize {
+        self.pattern
+            .char_indices()
+            .nth(chars)
+            .map_or(self.pattern.len(), |(at, _)| at)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.kind)?;
+
+        // Only the line where the span starts is shown, and underlined up to
+        // its end.
+        let chars: Vec<char> = self.pattern.chars().collect();
+        let line_start = chars[..self.span.start]
+            .iter()
+            .rposition(|&c| c == '\n')
+            .map_or(0, |at| at + 1);
+        let line_end = chars[self.span.start..]
+            .iter()
+            .position(|&c| c == '\n')
+            .map_or(chars.len(), |at| self.span.start + at);
+
+        let line: String = chars[line_start..line_end].iter().collect();
+        // Tabs are kept so that the carets line up with the pattern.
+        let indent: String = chars[line_start..self.span.start]
+            .iter()
+            .map(|&c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = (self.span.end.min(line_end) - self.span.start).max(1);
+
+        writeln!(f, "    {}", line)?;
+        write!(f, "    {}{}", indent, "^".repeat(width))?;
+        if let Some(help) = &self.help {
+            write!(f, "\nhelp: {}", help)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {}
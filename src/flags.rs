@@ -0,0 +1,96 @@
+use crate::assertion::Assertion;
+use crate::class::CharClass;
+
+/// Matching options shared by the front ends. Inside a pattern they can be
+/// toggled with `(?flags)` and `(?flags:...)`, using the letter noted on each.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Flags {
+    /// `i`: letters match regardless of case.
+    pub case_insensitive: bool,
+    /// `s`: `.` also matches `\n`.
+    pub dot_all: bool,
+    /// `u`: shorthand classes such as `\d` and `\w`, and the word boundaries
+    /// `\b` and `\B`, use Unicode definitions instead of ASCII ones.
+    pub unicode: bool,
+    /// `m`: `^` and `$` match at line boundaries instead of only at the ends of
+    /// the text.
+    pub multi_line: bool,
+    /// `x`: verbose mode.
+    pub verbose: bool,
+}
+
+impl Flags {
+    pub fn new() -> Flags {
+        Flags::default()
+    }
+
+    /// Turns the flag with letter `flag` on or off. Returns `false` if there is
+    /// no such flag.
+    pub fn set(&mut self, flag: char, enabled: bool) -> bool {
+        let field = match flag {
+            'i' => &mut self.case_insensitive,
+            's' => &mut self.dot_all,
+            'u' => &mut self.unicode,
+            'm' => &mut self.multi_line,
+            'x' => &mut self.verbose,
+            _ => return false,
+        };
+
+        *field = enabled;
+        true
+    }
+
+    /// The set of characters matched by `.` under these flags.
+    pub fn dot_class(&self) -> CharClass {
+        if self.dot_all {
+            CharClass::any()
+        } else {
+            CharClass::from_char('\n').negate()
+        }
+    }
+
+    /// The assertion `^` stands for under these flags.
+    pub fn caret(&self) -> Assertion {
+        if self.multi_line {
+            Assertion::StartLine
+        } else {
+            Assertion::StartText
+        }
+    }
+
+    /// The assertion `$` stands for under these flags.
+    pub fn dollar(&self) -> Assertion {
+        if self.multi_line {
+            Assertion::EndLine
+        } else {
+            Assertion::EndText
+        }
+    }
+
+    /// The assertion `\b`, or `\B` if `negated`, stands for under these flags.
+    pub fn word_boundary(&self, negated: bool) -> Assertion {
+        match (self.unicode, negated) {
+            (false, false) => Assertion::AsciiWordBoundary,
+            (false, true) => Assertion::AsciiNotWordBoundary,
+            (true, false) => Assertion::UnicodeWordBoundary,
+            (true, true) => Assertion::UnicodeNotWordBoundary,
+        }
+    }
+
+    /// The set of characters matched by the shorthand `\<name>` under these
+    /// flags, or `None` if `name` is not one of `d D w W s S`.
+    pub fn shorthand_class(&self, name: char) -> Option<CharClass> {
+        let class = match name.to_ascii_lowercase() {
+            'd' => CharClass::digit(self.unicode),
+            'w' => CharClass::word(self.unicode),
+            's' => CharClass::space(self.unicode),
+            _ => return None,
+        };
+
+        if name.is_ascii_uppercase() {
+            Some(class.negate())
+        } else {
+            Some(class)
+        }
+    }
+}
@@ -1,4 +1,13 @@
+pub mod assertion;
+pub mod ast;
+pub mod class;
+pub mod error;
+pub mod flags;
+pub mod options;
 pub mod pda;
 pub mod regex0;
 pub mod regex1;
 pub mod regex2;
+mod states;
+pub mod syntax;
+mod tables;
@@ -1,6 +1,4 @@
-use regex::regex1::nfa::recognize;
-use regex::regex1::parser::{to_postfix, with_explicit_concat};
-use regex::regex1::{create_matcher, main0};
+use regex::regex1::main0;
 
 fn main() -> Result<(), String> {
     main0()
@@ -0,0 +1,38 @@
+//! Options for compiling a pattern, taken by the `*_with_options` entry point
+//! of every front end.
+
+use crate::flags::Flags;
+use crate::syntax::NESTING_LIMIT;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Options {
+    /// The flags in effect at the start of the pattern.
+    pub flags: Flags,
+    /// How deeply groups and classes may nest, at most `MAX_NESTING_LIMIT`.
+    /// Deeper patterns are rejected with `ErrorKind::NestingTooDeep`.
+    pub nesting_limit: usize,
+}
+
+impl Options {
+    pub fn new() -> Options {
+        Options::default()
+    }
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            flags: Flags::default(),
+            nesting_limit: NESTING_LIMIT,
+        }
+    }
+}
+
+impl From<Flags> for Options {
+    fn from(flags: Flags) -> Options {
+        Options {
+            flags,
+            ..Options::default()
+        }
+    }
+}
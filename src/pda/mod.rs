@@ -1,37 +1,392 @@
-use std::collections::HashMap;
-
-struct PDA {
-    start_input: String,
-    found: usize,
-    accepted_config: Vec<usize>,
-    production_rules: HashMap<char, char>,
-    states: Vec<usize>,
-    symbols: Vec<usize>,
-    stack_symbols: Vec<usize>,
-    start_symbol: String,
-    stack_start: String,
-    acceptable_states: Vec<usize>,
-    accept_with: String,
+//! A pushdown automaton for the patterns of `regex1` that call groups
+//! recursively: `(?R)`, `(?1)`, `(?&name)` and `(?P>name)`. Such patterns
+//! describe nested structures, like balanced parentheses, which no finite
+//! automaton can recognize.
+//!
+//! Each group is a procedure of the automaton, the whole pattern being
+//! procedure 0. A call pushes the state to return to on the stack and jumps to
+//! the start of the called group; reaching the end of that group pops it.
+//! Groups that are not called are matched inline, as in the NFA of `regex1`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::assertion::Assertion;
+use crate::ast::{Ast, AstKind, Greed, GroupKind, Repetition};
+use crate::class::CharClass;
+use crate::error::Error;
+use crate::flags::Flags;
+use crate::options::Options;
+use crate::regex1::parser::Parser;
+
+#[derive(Debug, Default)]
+struct PState {
+    transitions: Vec<(CharClass, usize)>,
+    epsilon_transitions: Vec<usize>,
+    assertion: Option<Assertion>,
+    /// Calls this group, then goes on with the only epsilon transition.
+    call: Option<usize>,
+    /// Ends this group, returning to the caller if it was called.
+    returns: Option<usize>,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug)]
+pub struct PDA {
+    states: Vec<PState>,
+    start: usize,
+    /// The start and end states of each group, by index.
+    procedures: Vec<Option<(usize, usize)>>,
+}
+
+/// A frame of the stack: the group called and the state to return to.
+type Frame = (usize, usize);
+
+pub fn compile(regex: &str) -> Result<PDA, Error> {
+    compile_with_flags(regex, Flags::default())
+}
+
+pub fn compile_with_flags(regex: &str, flags: Flags) -> Result<PDA, Error> {
+    compile_with_options(regex, flags.into())
+}
+
+pub fn compile_with_options(regex: &str, options: Options) -> Result<PDA, Error> {
+    let mut builder = Builder {
+        pattern: regex,
+        states: vec![],
+        procedures: vec![],
+    };
+
+    let (start, end) = if regex.is_empty() {
+        let state = builder.state();
+        (state, state)
+    } else {
+        let mut parser = Parser::with_options(regex.to_string(), options);
+        let ast = parser.parse()?;
+        builder.procedures = vec![None; parser.group_names().len()];
+        ast.fold(copies, |ast, fragments| builder.build(ast, fragments))?
+    };
+
+    let accept = builder.state();
+    builder.states[accept].returns = Some(0);
+    builder.states[end].epsilon_transitions.push(accept);
+    builder
+        .procedures
+        .resize(builder.procedures.len().max(1), None);
+    builder.procedures[0] = Some((start, accept));
+
+    Ok(PDA {
+        states: builder.states,
+        start,
+        procedures: builder.procedures,
+    })
+}
+
+/// Whether `pda` matches anywhere in `haystack`.
+pub fn is_match(pda: &PDA, haystack: &str) -> bool {
+    matches(pda, haystack, false)
+}
+
+/// Whether `pda` matches the whole of `haystack`.
+pub fn is_full_match(pda: &PDA, haystack: &str) -> bool {
+    matches(pda, haystack, true)
+}
+
+// Whether `pda` matches in `haystack`, starting and ending anywhere or, if
+// `full`, at its ends.
+fn matches(pda: &PDA, haystack: &str, full: bool) -> bool {
+    let mut stacks = Stacks::default();
+    let mut configs: Vec<(usize, usize)> = vec![];
+    for (at, c) in haystack
+        .char_indices()
+        .map(|(at, c)| (at, Some(c)))
+        .chain(std::iter::once((haystack.len(), None)))
+    {
+        if !full || at == 0 {
+            configs.push((pda.start, EMPTY));
+        }
+        let prev = haystack[..at].chars().next_back();
+        let (waiting, accepted) = pda.closure(&mut stacks, configs, at, prev, c);
+        if accepted && (!full || c.is_none()) {
+            return true;
+        }
+
+        let c = match c {
+            Some(c) => c,
+            None => break,
+        };
+        configs = vec![];
+        for (state, stack) in waiting {
+            for (class, to) in &pda.states[state].transitions {
+                if class.contains(c) {
+                    configs.push((*to, stack));
+                }
+            }
+        }
+    }
+
+    false
+}
+
+/// The empty stack, see `Stacks`.
+const EMPTY: usize = usize::MAX;
+
+/// The stacks of all configurations, sharing their frames in a graph: the
+/// frames pushed at the same position by calls of the same group returning to
+/// the same state are one node, whose parents are the nodes under it in every
+/// stack it is on. A stack is named by its top node, or `EMPTY`. The number of
+/// nodes is then bounded by the positions times the frames, instead of growing
+/// with every path through the pattern.
+#[derive(Default)]
+struct Stacks {
+    nodes: Vec<(Frame, Vec<usize>)>,
+    index: HashMap<(Frame, usize), usize>,
+}
+
+impl Stacks {
+    /// Pushes `frame` at position `at` on `stack`. Returns the new top, and
+    /// whether it is a new node rather than one shared with other stacks.
+    fn push(&mut self, frame: Frame, at: usize, stack: usize) -> (usize, bool) {
+        if let Some(&node) = self.index.get(&(frame, at)) {
+            let parents = &mut self.nodes[node].1;
+            if !parents.contains(&stack) {
+                parents.push(stack);
+            }
+            return (node, false);
+        }
+
+        self.nodes.push((frame, vec![stack]));
+        let node = self.nodes.len() - 1;
+        self.index.insert((frame, at), node);
+        (node, true)
+    }
 }
 
 impl PDA {
-    fn generate(&self) -> usize {
-        let total = 0usize;
+    /// Follows the epsilon transitions, calls and returns from `configs`, each
+    /// a state and a stack of `stacks`, at position `at` between the
+    /// characters `prev` and `next`. Returns the configurations waiting for a
+    /// character, and whether the whole pattern was matched.
+    fn closure(
+        &self,
+        stacks: &mut Stacks,
+        mut configs: Vec<(usize, usize)>,
+        at: usize,
+        prev: Option<char>,
+        next: Option<char>,
+    ) -> (Vec<(usize, usize)>, bool) {
+        let mut seen = HashSet::new();
+        // The nodes popped at this position, for the stacks that share them
+        // only after they were popped.
+        let mut popped = HashSet::new();
+        let mut waiting = vec![];
+        let mut accepted = false;
+
+        while let Some((state, stack)) = configs.pop() {
+            if !seen.insert((state, stack)) {
+                continue;
+            }
+            let s = &self.states[state];
+            if s.assertion.is_some_and(|a| !a.holds(prev, next)) {
+                continue;
+            }
+
+            if let Some(group) = s.call {
+                if let Some((start, _)) = self.procedures[group] {
+                    let ret = s.epsilon_transitions[0];
+                    let (top, new) = stacks.push((group, ret), at, stack);
+                    if new {
+                        configs.push((start, top));
+                    } else if popped.contains(&top) {
+                        configs.push((ret, stack));
+                    }
+                }
+                continue;
+            }
+            if let Some(group) = s.returns {
+                if stack == EMPTY {
+                    if group == 0 {
+                        accepted = true;
+                    }
+                } else {
+                    let ((called, ret), parents) = &stacks.nodes[stack];
+                    if *called == group {
+                        popped.insert(stack);
+                        configs.extend(parents.iter().map(|&parent| (*ret, parent)));
+                        continue;
+                    }
+                }
+            }
+
+            for &to in &s.epsilon_transitions {
+                configs.push((to, stack));
+            }
+            if !s.transitions.is_empty() {
+                waiting.push((state, stack));
+            }
+        }
+
+        (waiting, accepted)
+    }
+}
 
-        if self.found > 0 {
-            return 0;
-        } else {
-            return 1;
+// ----------
+
+/// Builds the states of the tree of a pattern. Each node gives a fragment, a
+/// start and an end state, the end having no transitions yet.
+struct Builder<'p> {
+    pattern: &'p str,
+    states: Vec<PState>,
+    procedures: Vec<Option<(usize, usize)>>,
+}
+
+impl Builder<'_> {
+    fn state(&mut self) -> usize {
+        self.states.push(PState::default());
+        self.states.len() - 1
+    }
+
+    fn epsilon(&mut self, from: usize, to: usize) {
+        self.states[from].epsilon_transitions.push(to);
+    }
+
+    fn symbol(&mut self, class: CharClass) -> (usize, usize) {
+        let start = self.state();
+        let end = self.state();
+        self.states[start].transitions.push((class, end));
+        (start, end)
+    }
+
+    /// Builds the fragment of `ast` from `fragments`, those of its children,
+    /// see `Ast::fold`.
+    fn build(
+        &mut self,
+        ast: &Ast,
+        fragments: Vec<(usize, usize)>,
+    ) -> Result<(usize, usize), Error> {
+        let mut fragments = fragments.into_iter();
+        match &ast.kind {
+            AstKind::Alternation(_) => {
+                let start = self.state();
+                let end = self.state();
+                for branch in fragments {
+                    self.epsilon(start, branch.0);
+                    self.epsilon(branch.1, end);
+                }
+                Ok((start, end))
+            }
+            AstKind::Concat(_) => {
+                let (start, mut end) = fragments.next().unwrap();
+                for factor in fragments {
+                    self.epsilon(end, factor.0);
+                    end = factor.1;
+                }
+                Ok((start, end))
+            }
+            AstKind::Repetition(repetition) => {
+                if repetition.greed == Greed::Possessive {
+                    let reason = "possessive quantifiers are not supported by the pda";
+                    return Err(ast.unsupported(self.pattern, reason));
+                }
+                Ok(self.repeat(fragments, repetition.min, repetition.max))
+            }
+            AstKind::Literal(c) => Ok(self.symbol(CharClass::from_char(*c))),
+            AstKind::Class(class) => Ok(self.symbol(class.clone())),
+            AstKind::Assertion(assertion) => {
+                let state = self.state();
+                self.states[state].assertion = Some(*assertion);
+                Ok((state, state))
+            }
+            AstKind::Group(group) => match group.kind {
+                GroupKind::Capture(index) => {
+                    let (start, inner) = fragments.next().unwrap();
+                    let end = self.state();
+                    self.epsilon(inner, end);
+                    self.states[end].returns = Some(index);
+                    // A group repeated by a quantifier is built more than once;
+                    // calls go to its first copy.
+                    if self.procedures[index].is_none() {
+                        self.procedures[index] = Some((start, end));
+                    }
+                    Ok((start, end))
+                }
+                GroupKind::NonCapture => Ok(fragments.next().unwrap()),
+                GroupKind::Define => {
+                    // Only built for its groups to be called, so left unconnected.
+                    let state = self.state();
+                    Ok((state, state))
+                }
+                GroupKind::Atomic => {
+                    Err(ast.unsupported(self.pattern, "atomic groups are not supported by the pda"))
+                }
+                GroupKind::Look { .. } => {
+                    Err(ast.unsupported(self.pattern, "lookarounds are not supported by the pda"))
+                }
+            },
+            AstKind::Call(index) => {
+                let call = self.state();
+                let ret = self.state();
+                self.states[call].call = Some(*index);
+                self.epsilon(call, ret);
+                Ok((call, ret))
+            }
+            AstKind::Empty => {
+                let state = self.state();
+                Ok((state, state))
+            }
+            AstKind::Backreference { .. } => {
+                Err(ast.unsupported(self.pattern, "backreferences are not supported by the pda"))
+            }
+            AstKind::Conditional(_) => {
+                Err(ast.unsupported(self.pattern, "conditionals are not supported by the pda"))
+            }
+            AstKind::Intersection(_) | AstKind::Complement(_) => Err(ast.unsupported(
+                self.pattern,
+                "intersections and complements are not supported by the pda",
+            )),
         }
     }
 
-    fn is_found(&self, state: usize, input: &str, stack: usize) -> bool {
-        if input.len() > 0 {
-            return false;
+    /// Builds an atom repeated between `min` and `max` times from `copies`,
+    /// one per mandatory or optional repetition, see `copies`.
+    fn repeat<I>(&mut self, mut copies: I, min: usize, max: Option<usize>) -> (usize, usize)
+    where
+        I: Iterator<Item = (usize, usize)>,
+    {
+        let start = self.state();
+        let end = self.state();
+        let mut last = start;
+        for copy in copies.by_ref().take(min) {
+            self.epsilon(last, copy.0);
+            last = copy.1;
+        }
+
+        match max {
+            None => {
+                let copy = copies.next().unwrap();
+                self.epsilon(last, copy.0);
+                self.epsilon(copy.1, copy.0);
+                self.epsilon(copy.1, end);
+            }
+            // Still built, so that the groups inside can be called.
+            Some(0) if min == 0 => {}
+            Some(_) => {
+                for copy in copies {
+                    self.epsilon(last, copy.0);
+                    self.epsilon(last, end);
+                    last = copy.1;
+                }
+            }
         }
-        todo!()
-        // if self.accept_with == "E" {
-        //     return self.stack
-        // }
+        self.epsilon(last, end);
+        (start, end)
+    }
+}
+
+// How many copies of the atom of `repetition` its fragment is made of, see
+// `Builder::repeat`.
+fn copies(repetition: &Repetition) -> usize {
+    match repetition.max {
+        None => repetition.min + 1,
+        Some(max) => max.max(1),
     }
 }
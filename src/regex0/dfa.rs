@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::class::CharClass;
+use crate::regex0::constructs::SState;
+use crate::regex0::regex::NFA;
+
+/// A complete deterministic automaton: every state has a transition on every
+/// character, possibly to a dead state. State 0 is the start state.
+///
+/// This is what the `&` and `~` operators are computed on, since neither has a
+/// Thompson construction.
+pub struct DFA {
+    /// The transitions out of each state, on disjoint classes covering every
+    /// character.
+    pub(super) transitions: Vec<Vec<(CharClass, usize)>>,
+    pub(super) accepting: Vec<bool>,
+}
+
+impl DFA {
+    /// Determinizes `nfa` with the subset construction. Assertions depend on
+    /// the characters around them, which a DFA does not see, so they are
+    /// rejected.
+    pub fn from_nfa(nfa: &NFA) -> Result<DFA, String> {
+        let mut dfa = DFA {
+            transitions: vec![],
+            accepting: vec![],
+        };
+        let mut subsets: Vec<Vec<SState>> = vec![closure(&[Rc::clone(&nfa.start)])?];
+        let mut ids: HashMap<Vec<String>, usize> = HashMap::new();
+        ids.insert(names(&subsets[0]), 0);
+
+        while dfa.transitions.len() < subsets.len() {
+            let subset = subsets[dfa.transitions.len()].clone();
+
+            // Splits the characters by the set of states they lead to.
+            let mut parts: Vec<(CharClass, Vec<SState>)> = vec![(CharClass::any(), vec![])];
+            for state in &subset {
+                for (class, to) in &state.borrow().transitions {
+                    let mut refined = vec![];
+                    for (part, targets) in parts {
+                        let outside = part.difference(class);
+                        if !outside.is_empty() {
+                            refined.push((outside, targets.clone()));
+                        }
+                        let inside = part.intersect(class);
+                        if !inside.is_empty() {
+                            let mut targets = targets;
+                            targets.push(Rc::clone(to));
+                            refined.push((inside, targets));
+                        }
+                    }
+                    parts = refined;
+                }
+            }
+
+            let mut transitions: Vec<(CharClass, usize)> = vec![];
+            for (class, targets) in parts {
+                let target = closure(&targets)?;
+                let key = names(&target);
+                let id = match ids.get(&key) {
+                    Some(&id) => id,
+                    None => {
+                        ids.insert(key, subsets.len());
+                        subsets.push(target);
+                        subsets.len() - 1
+                    }
+                };
+                match transitions.iter_mut().find(|(_, to)| *to == id) {
+                    Some((merged, _)) => *merged = merged.union(&class),
+                    None => transitions.push((class, id)),
+                }
+            }
+
+            dfa.accepting
+                .push(subset.iter().any(|state| Rc::ptr_eq(state, &nfa.end)));
+            dfa.transitions.push(transitions);
+        }
+
+        Ok(dfa)
+    }
+
+    /// Accepts exactly the strings this DFA rejects.
+    pub fn complement(mut self) -> DFA {
+        for accepting in &mut self.accepting {
+            *accepting = !*accepting;
+        }
+        self
+    }
+
+    /// The product construction: runs both DFAs side by side, accepting where
+    /// both accept.
+    pub fn intersect(&self, other: &DFA) -> DFA {
+        let mut product = DFA {
+            transitions: vec![],
+            accepting: vec![],
+        };
+        let mut pairs = vec![(0, 0)];
+        let mut ids: HashMap<(usize, usize), usize> = HashMap::new();
+        ids.insert((0, 0), 0);
+
+        while product.transitions.len() < pairs.len() {
+            let (p, q) = pairs[product.transitions.len()];
+
+            let mut transitions = vec![];
+            for (c1, to1) in &self.transitions[p] {
+                for (c2, to2) in &other.transitions[q] {
+                    let class = c1.intersect(c2);
+                    if class.is_empty() {
+                        continue;
+                    }
+                    let id = *ids.entry((*to1, *to2)).or_insert_with(|| {
+                        pairs.push((*to1, *to2));
+                        pairs.len() - 1
+                    });
+                    transitions.push((class, id));
+                }
+            }
+
+            product
+                .accepting
+                .push(self.accepting[p] && other.accepting[q]);
+            product.transitions.push(transitions);
+        }
+
+        product
+    }
+}
+
+/// The states reachable from `states` by epsilon transitions, sorted by name.
+fn closure(states: &[SState]) -> Result<Vec<SState>, String> {
+    let mut reached: Vec<SState> = vec![];
+    let mut pending = states.to_vec();
+    while let Some(state) = pending.pop() {
+        if reached.contains(&state) {
+            continue;
+        }
+        if state.borrow().assertion.is_some() {
+            return Err("assertions cannot be used inside `&` and `~`".to_string());
+        }
+        pending.extend(state.borrow().epsilon.iter().cloned());
+        reached.push(state);
+    }
+
+    reached.sort_by(|a, b| a.borrow().name.cmp(&b.borrow().name));
+    Ok(reached)
+}
+
+fn names(states: &[SState]) -> Vec<String> {
+    states
+        .iter()
+        .map(|state| state.borrow().name.clone())
+        .collect()
+}
@@ -1,30 +1,50 @@
 use std::cell::RefCell;
-use std::error::Error;
+use std::collections::HashSet;
 use std::rc::Rc;
 
-use crate::regex0::constructs::{SState, State, Token};
-use crate::regex0::functionals::{Lexer, Parser};
+use crate::assertion::Assertion;
+use crate::ast::{Ast, AstKind, Greed, Group, GroupKind, Repetition};
+use crate::class::CharClass;
+use crate::error::{Error, ErrorKind};
+use crate::flags::Flags;
+use crate::options::Options;
+use crate::regex0::constructs::{SState, State};
+use crate::regex0::dfa::DFA;
+use crate::regex1::parser::Parser;
+use crate::syntax::Syntax;
 
 // ******* BE WARNED !!! There are mem leaks in the following code! *******
 
 pub struct NFA {
-    start: SState,
-    end: SState,
+    pub(super) start: SState,
+    pub(super) end: SState,
 }
 
-fn add_state(state: &SState, states: &mut Vec<SState>) {
-    // Already contains this state? skip.
-    for s in states.iter() {
-        if s == state {
-            return;
+// The states reachable from `starts` by epsilon transitions, `starts`
+// included. `prev` and `next` are the characters around the current position,
+// used to check assertions. The states to visit are kept on an explicit stack,
+// as chains of epsilon transitions can be as long as the pattern.
+fn closure(starts: Vec<SState>, prev: Option<char>, next: Option<char>) -> Vec<SState> {
+    let mut states = vec![];
+    let mut seen: HashSet<*const RefCell<State>> = HashSet::new();
+    let mut stack = starts;
+    stack.reverse();
+    while let Some(state) = stack.pop() {
+        // Already contains this state? skip.
+        if !seen.insert(Rc::as_ptr(&state)) {
+            continue;
         }
-    }
+        states.push(Rc::clone(&state));
 
-    let v = Rc::clone(state);
-    states.append(&mut vec![v]);
-    for e in &state.borrow().epsilon {
-        add_state(&e, states);
+        let state = state.borrow();
+        if let Some(assertion) = state.assertion {
+            if !assertion.holds(prev, next) {
+                continue;
+            }
+        }
+        stack.extend(state.epsilon.iter().rev().cloned());
     }
+    states
 }
 
 impl NFA {
@@ -34,19 +54,20 @@ impl NFA {
     }
 
     pub fn match_regex(&mut self, to_match: &str) -> bool {
-        let mut current_states = Vec::<SState>::new();
-        add_state(&self.start, &mut current_states);
+        let mut chars = to_match.chars().peekable();
+        let mut current_states = closure(vec![Rc::clone(&self.start)], None, chars.peek().copied());
 
-        for c in to_match.chars() {
-            let mut next_states = Vec::<SState>::new();
+        while let Some(c) = chars.next() {
+            let next = chars.peek().copied();
+            let mut targets = Vec::<SState>::new();
             for state in current_states {
-                if state.borrow().transitions.contains_key(&c) {
-                    let x = state.borrow();
-                    let trans_state = x.transitions.get(&c).unwrap();
-                    add_state(trans_state, &mut next_states);
+                for (class, trans_state) in &state.borrow().transitions {
+                    if class.contains(c) {
+                        targets.push(Rc::clone(trans_state));
+                    }
                 }
             }
-            current_states = next_states;
+            current_states = closure(targets, Some(c), next);
         }
 
         for s in current_states {
@@ -60,51 +81,36 @@ impl NFA {
 
 // ----------------------------------
 
-struct Handler {
+struct Handler<'p> {
+    // The pattern the tree was parsed from, for errors to point into.
+    pattern: &'p str,
     state_count: usize,
 }
 
-impl Handler {
+impl Handler<'_> {
     fn create_state(&mut self) -> Rc<RefCell<State>> {
         self.state_count += 1;
         Rc::new(RefCell::new(State::new(format!("s{}", self.state_count))))
     }
 
-    fn handle_char(&mut self, t: &Token, nfa_stack: &mut Vec<NFA>) -> Result<(), String> {
-        let v: char = if let Token::Char(v) = *t {
-            v
-        } else {
-            return Err(format!("expecting Token::Char, got={}", *t));
-        };
-
+    fn handle_class(&mut self, class: CharClass) -> NFA {
         let s0 = self.create_state();
         let s1 = self.create_state();
-        s0.borrow_mut()
-            .transitions
-            .entry(v)
-            .or_insert_with(|| Rc::clone(&s1));
+        s0.borrow_mut().transitions.push((class, Rc::clone(&s1)));
 
-        let nfa = NFA::new(s0, s1);
-        let mut nfa = vec![nfa];
-        nfa_stack.append(&mut nfa);
-        Ok(())
+        NFA::new(s0, s1)
     }
 
-    fn handle_concat(&mut self, _t: &Token, nfa_stack: &mut Vec<NFA>) {
-        let n2 = nfa_stack.pop().unwrap();
-        let n1 = nfa_stack.pop().unwrap();
+    fn handle_concat(&mut self, n1: NFA, n2: NFA) -> NFA {
         n1.end.borrow_mut().is_end = false;
         n1.end
             .borrow_mut()
             .epsilon
             .append(&mut vec![Rc::clone(&n2.start)]);
-        let nfa = NFA::new(n1.start, n2.end);
-        nfa_stack.append(&mut vec![nfa]);
+        NFA::new(n1.start, n2.end)
     }
 
-    fn handle_alt(&mut self, _t: &Token, nfa_stack: &mut Vec<NFA>) {
-        let n2 = nfa_stack.pop().unwrap();
-        let n1 = nfa_stack.pop().unwrap();
+    fn handle_alt(&mut self, n1: NFA, n2: NFA) -> NFA {
         let s0 = self.create_state();
         s0.borrow_mut()
             .epsilon
@@ -120,12 +126,11 @@ impl Handler {
             .append(&mut vec![Rc::clone(&s3)]);
         n1.end.borrow_mut().is_end = false;
         n2.end.borrow_mut().is_end = false;
-        let nfa = NFA::new(s0, s3);
-        nfa_stack.append(&mut vec![nfa]);
+        NFA::new(s0, s3)
     }
 
-    fn handle_rep(&mut self, t: &Token, nfa_stack: &mut Vec<NFA>) {
-        let n1 = nfa_stack.pop().unwrap();
+    // `n1*`, or `n1+` if `at_least_once`.
+    fn handle_rep(&mut self, n1: NFA, at_least_once: bool) -> NFA {
         let s0 = self.create_state();
         let s1 = self.create_state();
 
@@ -133,7 +138,7 @@ impl Handler {
             .epsilon
             .append(&mut vec![Rc::clone(&n1.start)]);
 
-        if let Token::Star = t {
+        if !at_least_once {
             s0.borrow_mut().epsilon.append(&mut vec![Rc::clone(&s1)]);
         }
 
@@ -141,61 +146,200 @@ impl Handler {
             .borrow_mut()
             .epsilon
             .append(&mut vec![Rc::clone(&s1), Rc::clone(&n1.start)]);
-        n1.end.borrow_mut().is_end = true;
+        n1.end.borrow_mut().is_end = false;
 
-        let nfa = NFA::new(s0, s1);
-        nfa_stack.append(&mut vec![nfa]);
+        NFA::new(s0, s1)
     }
 
-    fn handle_qmark(&mut self, _t: &Token, nfa_stack: &mut Vec<NFA>) {
-        let n1 = nfa_stack.pop().unwrap();
-        n1.start
-            .borrow_mut()
+    // A fresh start state skips `n1`: the one of `n1` may hold an assertion,
+    // which gates every epsilon leaving it, or be reentered by a loop.
+    fn handle_qmark(&mut self, n1: NFA) -> NFA {
+        let s0 = self.create_state();
+        s0.borrow_mut()
             .epsilon
-            .append(&mut vec![Rc::clone(&n1.end)]);
-        nfa_stack.append(&mut vec![n1]);
-    }
-
-    fn handle(&mut self, t: &Token, nfa_stack: &mut Vec<NFA>) -> Result<(), String> {
-        match t {
-            Token::Star => self.handle_rep(t, nfa_stack),
-            Token::Alt => self.handle_alt(t, nfa_stack),
-            Token::Concat => self.handle_concat(t, nfa_stack),
-            Token::Plus => self.handle_rep(t, nfa_stack),
-            Token::QMark => self.handle_qmark(t, nfa_stack),
-            Token::Char(_) => return self.handle_char(t, nfa_stack),
-            _ => {
-                return Err(format!("not expecting this token type: {}", t));
+            .append(&mut vec![Rc::clone(&n1.start), Rc::clone(&n1.end)]);
+        NFA::new(s0, n1.end)
+    }
+
+    /// Rewrites `x{min,max}` in terms of the other operators, as `min` copies
+    /// of `x` followed by either `x*` or `max - min` copies of `x?`, see
+    /// `copies`.
+    fn handle_repeat<I>(&mut self, copies: I, min: usize, max: Option<usize>) -> NFA
+    where
+        I: Iterator<Item = NFA>,
+    {
+        let mut nfa: Option<NFA> = None;
+        for (i, mut copy) in copies.enumerate() {
+            if i >= min {
+                copy = match max {
+                    Some(_) => self.handle_qmark(copy),
+                    None => self.handle_rep(copy, false),
+                };
             }
+            nfa = Some(match nfa {
+                Some(nfa) => self.handle_concat(nfa, copy),
+                None => copy,
+            });
         }
-        Ok(())
+
+        nfa.unwrap_or_else(|| self.handle_empty())
     }
 
-    fn new() -> Self {
-        Self { state_count: 0 }
+    fn handle_assertion(&mut self, assertion: Assertion) -> NFA {
+        let nfa = self.handle_empty();
+        nfa.start.borrow_mut().assertion = Some(assertion);
+        nfa
+    }
+
+    fn handle_empty(&mut self) -> NFA {
+        let s0 = self.create_state();
+        let s1 = self.create_state();
+        s0.borrow_mut().epsilon.append(&mut vec![Rc::clone(&s1)]);
+
+        NFA::new(s0, s1)
+    }
+
+    fn handle_and(&mut self, n1: NFA, n2: NFA) -> Result<NFA, String> {
+        let n1 = DFA::from_nfa(&n1)?;
+        let n2 = DFA::from_nfa(&n2)?;
+        Ok(self.handle_dfa(n1.intersect(&n2)))
     }
-}
 
-impl Default for Handler {
-    fn default() -> Self {
-        Handler::new()
+    fn handle_not(&mut self, n1: NFA) -> Result<NFA, String> {
+        let n1 = DFA::from_nfa(&n1)?;
+        Ok(self.handle_dfa(n1.complement()))
+    }
+
+    // Turns `dfa` back into an NFA, with epsilon transitions from its accepting
+    // states to a single end state.
+    fn handle_dfa(&mut self, dfa: DFA) -> NFA {
+        let states: Vec<SState> = dfa.accepting.iter().map(|_| self.create_state()).collect();
+        let end = self.create_state();
+
+        for (i, transitions) in dfa.transitions.into_iter().enumerate() {
+            let mut state = states[i].borrow_mut();
+            for (class, to) in transitions {
+                state.transitions.push((class, Rc::clone(&states[to])));
+            }
+            if dfa.accepting[i] {
+                state.epsilon.push(Rc::clone(&end));
+            }
+        }
+
+        NFA::new(Rc::clone(&states[0]), end)
+    }
+
+    // Joins `operands`, the NFAs of `asts`, the operands of `&`, `|` or a
+    // concatenation, left to right with `join`.
+    fn handle_all<I, F>(&mut self, asts: &[Ast], mut operands: I, mut join: F) -> Result<NFA, Error>
+    where
+        I: Iterator<Item = NFA>,
+        F: FnMut(&mut Self, NFA, NFA) -> Result<NFA, String>,
+    {
+        let mut nfa = operands.next().unwrap();
+        for (i, operand) in operands.enumerate() {
+            nfa = join(self, nfa, operand).map_err(|reason| {
+                let span = asts[0].span.start..asts[i + 1].span.end;
+                Error::new(ErrorKind::Unsupported(reason), self.pattern, span)
+            })?;
+        }
+        Ok(nfa)
+    }
+
+    // Builds the NFA of `ast` from `operands`, those of its children, see
+    // `Ast::fold`.
+    fn handle(&mut self, ast: &Ast, operands: Vec<NFA>) -> Result<NFA, Error> {
+        let mut operands = operands.into_iter();
+        let nfa = match &ast.kind {
+            AstKind::Empty => self.handle_empty(),
+            AstKind::Literal(c) => self.handle_class(CharClass::from_char(*c)),
+            AstKind::Class(class) => self.handle_class(class.clone()),
+            AstKind::Assertion(assertion) => self.handle_assertion(*assertion),
+            AstKind::Concat(asts) => {
+                return self.handle_all(asts, operands, |handler, n1, n2| {
+                    Ok(handler.handle_concat(n1, n2))
+                })
+            }
+            AstKind::Alternation(asts) => {
+                return self.handle_all(asts, operands, |handler, n1, n2| {
+                    Ok(handler.handle_alt(n1, n2))
+                })
+            }
+            AstKind::Intersection(asts) => {
+                return self.handle_all(asts, operands, Self::handle_and)
+            }
+            AstKind::Complement(_) => {
+                let n1 = operands.next().unwrap();
+                self.handle_not(n1)
+                    .map_err(|reason| ast.unsupported(self.pattern, reason))?
+            }
+            // Only whether the whole input matches is asked, so laziness does
+            // not matter.
+            AstKind::Repetition(repetition) if repetition.greed != Greed::Possessive => {
+                match (repetition.min, repetition.max) {
+                    (min @ (0 | 1), None) => {
+                        let n1 = operands.next().unwrap();
+                        self.handle_rep(n1, min == 1)
+                    }
+                    (0, Some(1)) => {
+                        let n1 = operands.next().unwrap();
+                        self.handle_qmark(n1)
+                    }
+                    (min, max) => self.handle_repeat(operands, min, max),
+                }
+            }
+            AstKind::Group(Group {
+                kind: GroupKind::Capture(_) | GroupKind::NonCapture,
+                ..
+            }) => operands.next().unwrap(),
+            AstKind::Group(Group {
+                kind: GroupKind::Look { .. },
+                ..
+            }) => {
+                return Err(ast.unsupported(self.pattern, "lookarounds are not supported by regex0"))
+            }
+            _ => {
+                let reason = "only the backtracking engine supports this, use `regex1::backtrack`";
+                return Err(ast.unsupported(self.pattern, reason));
+            }
+        };
+        Ok(nfa)
     }
 }
 
 // ----------------------------------
 
-pub fn compile(pattern: String) -> Result<NFA, Box<dyn Error>> {
-    let lexer = Lexer::new(pattern);
-    let mut parser = Parser::new(lexer);
-    let tokens = parser.parse(true)?;
+pub fn compile(pattern: String) -> Result<NFA, Error> {
+    compile_with_flags(pattern, Flags::default())
+}
 
-    let mut handler = Handler::new();
+/// Compiles `pattern`, in the syntax of `regex1` with the `&` intersection and
+/// `~` complement operators, see `Syntax::Boolean`.
+pub fn compile_with_flags(pattern: String, flags: Flags) -> Result<NFA, Error> {
+    compile_with_options(pattern, flags.into())
+}
 
-    let mut nfa_stack = vec![];
-    for t in tokens {
-        handler.handle(t, &mut nfa_stack)?;
+pub fn compile_with_options(pattern: String, options: Options) -> Result<NFA, Error> {
+    let mut parser = Parser::with_options(pattern.clone(), options);
+    parser.set_syntax(Syntax::Boolean);
+    let ast = parser.parse()?;
+    if let Some(error) = parser.unsupported(false) {
+        return Err(error);
     }
 
-    assert_eq!(nfa_stack.len(), 1);
-    Ok(nfa_stack.pop().unwrap())
+    let mut handler = Handler {
+        pattern: &pattern,
+        state_count: 0,
+    };
+    ast.fold(copies, |ast, operands| handler.handle(ast, operands))
+}
+
+// How many copies of the operand of `repetition` its NFA is made of, see
+// `Handler::handle_repeat`.
+fn copies(repetition: &Repetition) -> usize {
+    match (repetition.min, repetition.max) {
+        (0 | 1, None) | (0, Some(1)) => 1,
+        (min, None) => min + 1,
+        (_, Some(max)) => max,
+    }
 }
@@ -0,0 +1,201 @@
+//! A backtracking matcher over the same NFA as the automaton simulations in
+//! `nfa`. It tries the alternatives of each state one at a time in priority
+//! order, which lets it match backreferences, at the cost of exponential time
+//! on some patterns. Lookarounds are matched the same way, one position at a
+//! time and with the captures made so far, so they may contain backreferences.
+//! Atomic groups commit to the first match of their contents: once it is found
+//! the alternatives inside the group are dropped. Conditionals are checked
+//! against the captures made so far.
+
+use crate::error::Error;
+use crate::flags::Flags;
+use crate::options::Options;
+use crate::regex1::nfa::{parse_to_nfa, spans, Condition, Context, Look, Slots, State, NFA};
+
+/// Compiles `regex`, backreferences included, for use with this module's
+/// matchers.
+pub fn compile(regex: &str) -> Result<NFA, Error> {
+    compile_with_flags(regex, Flags::default())
+}
+
+pub fn compile_with_flags(regex: &str, flags: Flags) -> Result<NFA, Error> {
+    compile_with_options(regex, flags.into())
+}
+
+pub fn compile_with_options(regex: &str, options: Options) -> Result<NFA, Error> {
+    parse_to_nfa(regex, options, true)
+}
+
+pub fn is_match(nfa: &NFA, haystack: &str) -> bool {
+    find(nfa, haystack).is_some()
+}
+
+/// Whether `nfa` matches the whole of `haystack`.
+pub fn is_full_match(nfa: &NFA, haystack: &str) -> bool {
+    let slots = vec![None; 2 * nfa.captures_len()];
+    match_at(nfa, &Context::new(haystack), 0, Some(haystack.len()), slots).is_some()
+}
+
+/// Searches `haystack` for the leftmost match, returning its byte span. Agrees
+/// with `nfa::find` on patterns without backreferences.
+pub fn find(nfa: &NFA, haystack: &str) -> Option<(usize, usize)> {
+    captures(nfa, haystack).and_then(|groups| groups[0])
+}
+
+/// Like `find`, but also returns the span of every capture group, indexed as in
+/// `NFA::group_names`. Groups that did not participate in the match are `None`.
+pub fn captures(nfa: &NFA, haystack: &str) -> Option<Vec<Option<(usize, usize)>>> {
+    let cx = Context::new(haystack);
+    let slots = vec![None; 2 * nfa.captures_len()];
+    for start in boundaries(haystack) {
+        if let Some(slots) = match_at(nfa, &cx, start, None, slots.clone()) {
+            return Some(spans(&slots));
+        }
+    }
+    None
+}
+
+/// Whether `look`, before negation, holds at `at`. A lookbehind is tried from
+/// every earlier position, and must end exactly at `at`.
+fn look_matches(look: &Look, cx: &Context, at: usize, slots: &Slots) -> bool {
+    if look.ahead {
+        match_at(&look.nfa, cx, at, None, slots.clone()).is_some()
+    } else {
+        boundaries(&cx.haystack()[..at])
+            .any(|start| match_at(&look.nfa, cx, start, Some(at), slots.clone()).is_some())
+    }
+}
+
+/// Byte offsets of the char boundaries of `haystack`, its end included.
+fn boundaries(haystack: &str) -> impl Iterator<Item = usize> + '_ {
+    haystack
+        .char_indices()
+        .map(|(at, _)| at)
+        .chain(std::iter::once(haystack.len()))
+}
+
+/// A pending alternative: the state to try, the position, the capture slots,
+/// and the states passed since the last character was consumed, which stops
+/// loops that match the empty string from spinning forever.
+type Job = (State, usize, Slots, Vec<usize>);
+
+/// Tries to match `nfa` at `start`, ending anywhere or only at `end`. `slots`
+/// holds the captures made before `start`.
+fn match_at(
+    nfa: &NFA,
+    cx: &Context,
+    start: usize,
+    end: Option<usize>,
+    mut slots: Slots,
+) -> Option<Slots> {
+    let haystack = cx.haystack();
+    slots[0] = Some(start);
+
+    let mut jobs: Vec<Job> = vec![(nfa.start.clone(), start, slots, vec![])];
+    while let Some((state, mut at, mut slots, mut passed)) = jobs.pop() {
+        let s = state.borrow();
+        if passed.contains(&s.name) {
+            continue;
+        }
+        passed.push(s.name);
+
+        if !s.assertion_holds(cx, at) {
+            continue;
+        }
+        if let Some(look) = &s.look {
+            if look_matches(look, cx, at, &slots) == look.negated {
+                continue;
+            }
+        }
+        if let Some(slot) = s.slot {
+            slots[slot] = Some(at);
+        }
+        if let Some(inner) = &s.atomic {
+            let outer = (slots[0], slots[1]);
+            match match_at(inner, cx, at, None, slots) {
+                Some(inner_slots) => {
+                    slots = inner_slots;
+                    let end = slots[1].unwrap();
+                    if end != at {
+                        at = end;
+                        passed.clear();
+                    }
+                    slots[0] = outer.0;
+                    slots[1] = outer.1;
+                }
+                None => continue,
+            }
+        }
+        if let Some((group, case_insensitive)) = s.backref {
+            let len = match (slots[2 * group], slots[2 * group + 1]) {
+                (Some(from), Some(to)) => {
+                    match_again(&haystack[from..to], &haystack[at..], case_insensitive)
+                }
+                // A group that did not participate matches nothing.
+                _ => None,
+            };
+            match len {
+                Some(0) => {}
+                Some(len) => {
+                    at += len;
+                    passed.clear();
+                }
+                None => continue,
+            }
+        }
+
+        if s.is_end && end.is_some_and(|end| end != at) {
+            continue;
+        }
+        if s.is_end {
+            slots[1] = Some(at);
+            return Some(slots);
+        }
+
+        if let Some(condition) = &s.condition {
+            let holds = match condition {
+                Condition::Group(group) => slots[2 * group + 1].is_some(),
+                Condition::Look(look) => look_matches(look, cx, at, &slots) != look.negated,
+            };
+            let to = &s.epsilon_transitions[if holds { 0 } else { 1 }];
+            jobs.push((to.clone(), at, slots, passed));
+            continue;
+        }
+
+        // Jobs are popped last in first out, so push the preferred one last.
+        for to in s.epsilon_transitions.iter().rev() {
+            jobs.push((to.clone(), at, slots.clone(), passed.clone()));
+        }
+        if let Some(c) = haystack[at..].chars().next() {
+            for (class, to) in s.transitions.iter().rev() {
+                if class.contains(c) {
+                    jobs.push((to.clone(), at + c.len_utf8(), slots.clone(), vec![]));
+                }
+            }
+        }
+    }
+
+    None
+}
+
+/// Byte length of the prefix of `haystack` that matches `text`, if any.
+fn match_again(text: &str, haystack: &str, case_insensitive: bool) -> Option<usize> {
+    if !case_insensitive {
+        return if haystack.starts_with(text) {
+            Some(text.len())
+        } else {
+            None
+        };
+    }
+
+    let mut len = 0;
+    let mut rest = haystack.chars();
+    for expected in text.chars() {
+        let c = rest.next()?;
+        if c != expected && !c.to_lowercase().eq(expected.to_lowercase()) {
+            return None;
+        }
+        len += c.len_utf8();
+    }
+    Some(len)
+}
@@ -1,27 +1,45 @@
 /// Implementation inspired from:
 /// https://github.com/deniskyashif/regexjs
 ///
-use crate::regex1::nfa::{NFA, postfix_to_nfa, recognize};
-use crate::regex1::parser::{to_postfix, with_explicit_concat};
+use crate::error::Error;
+use crate::flags::Flags;
+use crate::options::Options;
+use crate::regex1::nfa::{ast_to_nfa, recognize, NFA};
+use crate::regex1::parser::Parser;
 
+pub mod backtrack;
 pub mod nfa;
 pub mod parser;
 
-pub fn create_matcher(exp: &str) -> Result<NFA, String> {
-    let explicit = with_explicit_concat(exp);
-    let postfix = to_postfix(&explicit);
-    postfix_to_nfa(&postfix)
+pub fn create_matcher(exp: &str) -> Result<NFA, Error> {
+    create_matcher_with_flags(exp, Flags::default())
+}
+
+/// Builds the NFA of `exp` without recursion, see `ast_to_nfa`. Like
+/// `nfa::infix_to_nfa`, this rejects what only `backtrack` can match.
+pub fn create_matcher_with_flags(exp: &str, flags: Flags) -> Result<NFA, Error> {
+    create_matcher_with_options(exp, flags.into())
+}
+
+pub fn create_matcher_with_options(exp: &str, options: Options) -> Result<NFA, Error> {
+    let mut parser = Parser::with_options(exp.to_string(), options);
+    let ast = parser.parse()?;
+    if let Some(error) = parser.unsupported(false) {
+        return Err(error);
+    }
+    let mut nfa = ast_to_nfa(exp, &ast)?;
+    nfa.group_names = parser.group_names().to_vec();
+    Ok(nfa)
 }
 
 pub fn main0() -> Result<(), String> {
     let exp = "ab*c(d?w)*|w";
-    let implicit = with_explicit_concat(exp);
-    println!("{}", implicit);
-
-    let postfix = to_postfix(&implicit);
-    println!("{}", postfix);
+    let ast = Parser::new(exp.to_string())
+        .parse()
+        .map_err(|e| e.to_string())?;
+    println!("{:?}", ast);
 
-    let nfa = create_matcher(exp)?;
+    let nfa = create_matcher(exp).map_err(|e| e.to_string())?;
 
     println!("{}", recognize(&nfa, "abc"));
     println!("{}", recognize(&nfa, "dac"));
@@ -1,21 +1,44 @@
 use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 
-use crate::regex1::parser::{Parser, TreeNode};
+use crate::assertion::Assertion;
+use crate::ast::{self, Ast, AstKind, Greed, GroupKind, Repetition};
+use crate::class::CharClass;
+use crate::error::Error;
+use crate::flags::Flags;
+use crate::options::Options;
+use crate::regex1::parser::Parser;
+use crate::states::{drop_successors, Successors};
 
 const INITIAL_CAPACITY: usize = 8;
 
-type State = Rc<RefCell<_State>>;
-
-struct _State {
-    name: usize,
-    is_end: bool,
-    transitions: HashMap<char, State>,
-    epsilon_transitions: Vec<State>,
+pub(super) type State = Rc<RefCell<_State>>;
+
+pub(super) struct _State {
+    pub(super) name: usize,
+    pub(super) is_end: bool,
+    pub(super) transitions: Vec<(CharClass, State)>,
+    pub(super) epsilon_transitions: Vec<State>,
+    // Epsilon transitions out of the state may only be taken where it holds.
+    pub(super) assertion: Option<Assertion>,
+    // Capture slot recording the position the state is passed at.
+    pub(super) slot: Option<usize>,
+    // Group whose text must be matched again before leaving the state, and
+    // whether to match it case-insensitively. Only the backtracking engine
+    // can follow these.
+    pub(super) backref: Option<(usize, bool)>,
+    // Lookaround that must hold for the state to be passed.
+    pub(super) look: Option<Rc<Look>>,
+    // Picks the first epsilon transition out of the state when it holds, the
+    // second otherwise. Only the backtracking engine can check it.
+    pub(super) condition: Option<Condition>,
+    // Atomic group to match before leaving the state. Only its preferred match
+    // is ever tried, which only the backtracking engine can follow.
+    pub(super) atomic: Option<Rc<NFA>>,
 }
 
 impl _State {
@@ -23,11 +46,32 @@ impl _State {
         _State {
             name,
             is_end,
-            transitions: HashMap::with_capacity(INITIAL_CAPACITY),
+            transitions: Vec::with_capacity(INITIAL_CAPACITY),
             epsilon_transitions: Vec::with_capacity(INITIAL_CAPACITY),
+            assertion: None,
+            slot: None,
+            backref: None,
+            look: None,
+            condition: None,
+            atomic: None,
         }
     }
 
+    pub(super) fn assertion_holds(&self, cx: &Context, at: usize) -> bool {
+        self.assertion
+            .is_none_or(|a| a.holds(cx.prev(at), cx.next(at)))
+    }
+
+    /// Checks the assertion and lookaround of the state, evaluating the
+    /// lookaround as automaton engines do.
+    fn passable(&self, cx: &Context, at: usize) -> bool {
+        self.assertion_holds(cx, at)
+            && self
+                .look
+                .as_ref()
+                .is_none_or(|look| cx.look_holds(look, at))
+    }
+
     fn from_start(name: usize) -> State {
         Self::new(name, false).into_cell()
     }
@@ -44,8 +88,34 @@ impl _State {
         self.epsilon_transitions.push(to);
     }
 
-    fn add_transition(&mut self, to: State, symbol: char) {
-        self.transitions.insert(symbol, to);
+    /// Epsilon transitions are tried in insertion order, so a greedy choice
+    /// prefers `enter` (one more iteration) and a lazy one prefers `leave`.
+    fn add_choice(&mut self, greedy: bool, enter: State, leave: State) {
+        if greedy {
+            self.add_epsilon_transition(enter);
+            self.add_epsilon_transition(leave);
+        } else {
+            self.add_epsilon_transition(leave);
+            self.add_epsilon_transition(enter);
+        }
+    }
+
+    fn add_transition(&mut self, to: State, class: CharClass) {
+        self.transitions.push((class, to));
+    }
+}
+
+impl Successors for _State {
+    fn take_successors(&mut self) -> Vec<State> {
+        let mut successors: Vec<State> = self.epsilon_transitions.drain(..).collect();
+        successors.extend(self.transitions.drain(..).map(|(_, to)| to));
+        successors
+    }
+}
+
+impl Drop for _State {
+    fn drop(&mut self) {
+        drop_successors(self);
     }
 }
 
@@ -59,7 +129,7 @@ impl Eq for _State {}
 
 impl PartialOrd for _State {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.name.cmp(&other.name))
+        Some(self.cmp(other))
     }
 }
 
@@ -94,6 +164,154 @@ impl Hash for _State {
     }
 }
 
+/// A lookaround, `(?=...)`, `(?!...)`, `(?<=...)` or `(?<!...)`.
+pub(super) struct Look {
+    id: usize,
+    pub(super) nfa: NFA,
+    // `nfa` turned around, to evaluate lookaheads scanning right to left.
+    reversed: NFA,
+    pub(super) ahead: bool,
+    pub(super) negated: bool,
+}
+
+impl Debug for Look {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Look[id={}, ahead={}, negated={}, nfa={}]",
+            self.id, self.ahead, self.negated, self.nfa
+        )
+    }
+}
+
+/// What a conditional group tests: whether a capture group participated in the
+/// match so far, or a lookaround.
+#[derive(Clone, Debug)]
+pub(super) enum Condition {
+    Group(usize),
+    Look(Rc<Look>),
+}
+
+/// The haystack being matched, along with the lookarounds evaluated so far.
+/// Automaton engines evaluate a lookaround at every position in a single linear
+/// scan the first time they need it, see `scan`.
+pub(super) struct Context<'h> {
+    haystack: &'h str,
+    // Whether each lookaround, before negation, holds at each byte position.
+    looks: RefCell<HashMap<usize, Vec<bool>>>,
+}
+
+impl<'h> Context<'h> {
+    pub(super) fn new(haystack: &'h str) -> Context<'h> {
+        Context {
+            haystack,
+            looks: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub(super) fn haystack(&self) -> &'h str {
+        self.haystack
+    }
+
+    pub(super) fn prev(&self, at: usize) -> Option<char> {
+        self.haystack[..at].chars().next_back()
+    }
+
+    pub(super) fn next(&self, at: usize) -> Option<char> {
+        self.haystack[at..].chars().next()
+    }
+
+    fn look_holds(&self, look: &Look, at: usize) -> bool {
+        let known = self.looks.borrow().get(&look.id).map(|table| table[at]);
+        let holds = match known {
+            Some(holds) => holds,
+            // No borrow is held while scanning, nested lookarounds need it.
+            None => {
+                let table = scan(look, self);
+                let holds = table[at];
+                self.looks.borrow_mut().insert(look.id, table);
+                holds
+            }
+        };
+
+        holds != look.negated
+    }
+}
+
+/// Evaluates `look` at every position of the haystack in one pass, by
+/// simulating its NFA with a new thread started at each position. Going left to
+/// right, a lookbehind holds wherever the end state is reached. Going right to
+/// left with the reversed NFA, a lookahead holds wherever its start state is.
+fn scan(look: &Look, cx: &Context) -> Vec<bool> {
+    let haystack = cx.haystack;
+    let nfa = if look.ahead {
+        &look.reversed
+    } else {
+        &look.nfa
+    };
+    let mut positions: Vec<usize> = haystack
+        .char_indices()
+        .map(|(at, _)| at)
+        .chain(std::iter::once(haystack.len()))
+        .collect();
+    if look.ahead {
+        positions.reverse();
+    }
+
+    let mut holds = vec![false; haystack.len() + 1];
+    let mut current: Vec<State> = vec![];
+    let mut seen: HashSet<usize> = HashSet::new();
+    for (i, &at) in positions.iter().enumerate() {
+        add_reachable(&nfa.start, cx, at, &mut current, &mut seen);
+        holds[at] = current.iter().any(|s| s.borrow().is_end);
+
+        let to = match positions.get(i + 1) {
+            Some(&to) => to,
+            None => break,
+        };
+        let symbol = haystack[at.min(to)..at.max(to)].chars().next().unwrap();
+        let mut next: Vec<State> = vec![];
+        let mut next_seen: HashSet<usize> = HashSet::new();
+        for state in &current {
+            for (class, s) in &state.borrow().transitions {
+                if class.contains(symbol) {
+                    add_reachable(s, cx, to, &mut next, &mut next_seen);
+                }
+            }
+        }
+        current = next;
+        seen = next_seen;
+    }
+
+    holds
+}
+
+/// Adds the states reachable from `state` by epsilon transitions at `at` that
+/// either consume a character or end the NFA.
+fn add_reachable(
+    state: &State,
+    cx: &Context,
+    at: usize,
+    states: &mut Vec<State>,
+    seen: &mut HashSet<usize>,
+) {
+    let mut stack = vec![state.clone()];
+    while let Some(state) = stack.pop() {
+        if !seen.insert(state.borrow().name) {
+            continue;
+        }
+
+        let s = state.borrow();
+        if !s.passable(cx, at) {
+            continue;
+        }
+        if s.is_end || !s.transitions.is_empty() {
+            states.push(state.clone());
+        }
+        stack.extend(s.epsilon_transitions.iter().cloned());
+    }
+}
+
 // -----------------------------------------------------------------------------
 
 type Namer = Rc<RefCell<dyn FnMut() -> usize>>;
@@ -110,13 +328,35 @@ fn new_namer() -> Namer {
 
 #[derive(Debug)]
 pub struct NFA {
-    start: State,
+    pub(super) start: State,
     end: State,
+    pub(super) group_names: Vec<Option<String>>,
 }
 
 impl NFA {
     fn new(start: State, end: State) -> NFA {
-        NFA { start, end }
+        NFA {
+            start,
+            end,
+            group_names: vec![None],
+        }
+    }
+
+    /// Number of capture groups, including group 0 for the whole match.
+    pub fn captures_len(&self) -> usize {
+        self.group_names.len()
+    }
+
+    /// Names of the capture groups by index, `None` for unnamed ones.
+    pub fn group_names(&self) -> &[Option<String>] {
+        &self.group_names
+    }
+
+    /// Index of the capture group called `name`.
+    pub fn group_index(&self, name: &str) -> Option<usize> {
+        self.group_names
+            .iter()
+            .position(|n| n.as_deref() == Some(name))
     }
 
     fn from_epsilon(namer: Namer) -> NFA {
@@ -127,9 +367,63 @@ impl NFA {
     }
 
     fn from_symbol(namer: Namer, symbol: char) -> NFA {
+        Self::from_class(namer, CharClass::from_char(symbol))
+    }
+
+    fn from_assertion(namer: Namer, assertion: Assertion) -> NFA {
+        let nfa = Self::from_epsilon(namer);
+        nfa.start.borrow_mut().assertion = Some(assertion);
+        nfa
+    }
+
+    fn from_backref(namer: Namer, index: usize, case_insensitive: bool) -> NFA {
+        let nfa = Self::from_epsilon(namer);
+        nfa.start.borrow_mut().backref = Some((index, case_insensitive));
+        nfa
+    }
+
+    fn from_look(namer: Namer, look: Rc<Look>) -> NFA {
+        let nfa = Self::from_epsilon(namer);
+        nfa.start.borrow_mut().look = Some(look);
+        nfa
+    }
+
+    /// Takes `yes` or `no` depending on `condition`. A missing `no` matches the
+    /// empty string.
+    fn from_conditional(namer: Namer, condition: Condition, mut yes: NFA, no: Option<NFA>) -> NFA {
+        let no = no.unwrap_or_else(|| NFA::from_epsilon(namer.clone()));
+        let nfa = yes.union(namer, no);
+        nfa.start.borrow_mut().condition = Some(condition);
+        nfa
+    }
+
+    fn from_atomic(namer: Namer, inner: NFA) -> NFA {
+        let nfa = Self::from_epsilon(namer);
+        nfa.start.borrow_mut().atomic = Some(Rc::new(inner));
+        nfa
+    }
+
+    /// Wraps `inner` in states recording the start and end of capture group
+    /// `index`.
+    fn from_group(namer: Namer, index: usize, inner: NFA) -> NFA {
+        let start = _State::from_start(namer.borrow_mut()());
+        let end = _State::from_end(namer.borrow_mut()());
+        start.borrow_mut().slot = Some(2 * index);
+        end.borrow_mut().slot = Some(2 * index + 1);
+
+        start
+            .borrow_mut()
+            .add_epsilon_transition(inner.start.clone());
+        inner.end.borrow_mut().add_epsilon_transition(end.clone());
+        inner.end.borrow_mut().is_end = false;
+
+        NFA::new(start, end)
+    }
+
+    fn from_class(namer: Namer, class: CharClass) -> NFA {
         let start = _State::from_start(namer.borrow_mut()());
         let end = _State::from_end(namer.borrow_mut()());
-        start.borrow_mut().add_transition(end.clone(), symbol);
+        start.borrow_mut().add_transition(end.clone(), class);
         Self::new(start, end)
     }
 
@@ -161,33 +455,30 @@ impl NFA {
         NFA::new(start, end)
     }
 
-    fn kleen_closure(self, namer: Namer) -> NFA {
+    fn kleen_closure(self, namer: Namer, greedy: bool) -> NFA {
         let start = _State::from_start(namer.borrow_mut()());
         let end = _State::from_end(namer.borrow_mut()());
 
-        start.borrow_mut().add_epsilon_transition(end.clone());
         start
             .borrow_mut()
-            .add_epsilon_transition(self.start.clone());
+            .add_choice(greedy, self.start.clone(), end.clone());
 
-        self.end.borrow_mut().add_epsilon_transition(end.clone());
         self.end
             .borrow_mut()
-            .add_epsilon_transition(self.start.clone());
+            .add_choice(greedy, self.start.clone(), end.clone());
 
         self.end.borrow_mut().is_end = false;
 
         NFA::new(start, end)
     }
 
-    fn zero_or_one(self, namer: Namer) -> NFA {
+    fn zero_or_one(self, namer: Namer, greedy: bool) -> NFA {
         let start = _State::from_start(namer.borrow_mut()());
         let end = _State::from_end(namer.borrow_mut()());
 
-        start.borrow_mut().add_epsilon_transition(end.clone());
         start
             .borrow_mut()
-            .add_epsilon_transition(self.start.clone());
+            .add_choice(greedy, self.start.clone(), end.clone());
 
         self.end.borrow_mut().add_epsilon_transition(end.clone());
 
@@ -196,7 +487,7 @@ impl NFA {
         NFA::new(start, end)
     }
 
-    fn one_or_more(self, namer: Namer) -> NFA {
+    fn one_or_more(self, namer: Namer, greedy: bool) -> NFA {
         let start = _State::from_start(namer.borrow_mut()());
         let end = _State::from_end(namer.borrow_mut()());
 
@@ -204,15 +495,133 @@ impl NFA {
             .borrow_mut()
             .add_epsilon_transition(self.start.clone());
 
-        self.end.borrow_mut().add_epsilon_transition(end.clone());
         self.end
             .borrow_mut()
-            .add_epsilon_transition(self.start.clone());
+            .add_choice(greedy, self.start.clone(), end.clone());
 
         self.end.borrow_mut().is_end = false;
 
         NFA::new(start, end)
     }
+
+    /// `x{min,max}` is expanded to `min` copies of `x` followed by either `x*`
+    /// or `max - min` copies of `x?`.
+    fn repeat(self, namer: Namer, min: usize, max: Option<usize>, greedy: bool) -> NFA {
+        let mut result = NFA::from_epsilon(namer.clone());
+
+        for _ in 0..min {
+            result = result.concat(self.duplicate(namer.clone()));
+        }
+
+        match max {
+            None => {
+                let rest = self.duplicate(namer.clone()).kleen_closure(namer, greedy);
+                result.concat(rest)
+            }
+            Some(max) => {
+                for _ in min..max {
+                    let rest = self
+                        .duplicate(namer.clone())
+                        .zero_or_one(namer.clone(), greedy);
+                    result = result.concat(rest);
+                }
+                result
+            }
+        }
+    }
+
+    /// Deep copy of the state graph, with fresh names for every state.
+    fn duplicate(&self, namer: Namer) -> NFA {
+        let mut copies: HashMap<usize, State> = HashMap::new();
+        let mut pending: Vec<State> = vec![self.start.clone()];
+
+        let mut copy_of = |state: &State, pending: &mut Vec<State>| {
+            let name = state.borrow().name;
+            copies
+                .entry(name)
+                .or_insert_with(|| {
+                    pending.push(state.clone());
+                    _State::new(namer.borrow_mut()(), state.borrow().is_end).into_cell()
+                })
+                .clone()
+        };
+
+        let start = copy_of(&self.start, &mut vec![]);
+        let mut visited: HashSet<usize> = HashSet::new();
+        while let Some(state) = pending.pop() {
+            let original = state.borrow();
+            if !visited.insert(original.name) {
+                continue;
+            }
+
+            let copy = copy_of(&state, &mut vec![]);
+            copy.borrow_mut().assertion = original.assertion;
+            copy.borrow_mut().slot = original.slot;
+            copy.borrow_mut().backref = original.backref;
+            copy.borrow_mut().look = original.look.clone();
+            copy.borrow_mut().atomic = original.atomic.clone();
+            copy.borrow_mut().condition = original.condition.clone();
+            for (class, to) in &original.transitions {
+                let to = copy_of(to, &mut pending);
+                copy.borrow_mut().add_transition(to, class.clone());
+            }
+            for to in &original.epsilon_transitions {
+                let to = copy_of(to, &mut pending);
+                copy.borrow_mut().add_epsilon_transition(to);
+            }
+        }
+
+        let end = copy_of(&self.end, &mut vec![]);
+        NFA::new(start, end)
+    }
+
+    /// The NFA of the reversed strings: every transition turned around and the
+    /// start and end swapped. Assertions and lookarounds stay on their states
+    /// since they only depend on the position. Capture slots, backreferences,
+    /// conditionals and atomic groups are dropped.
+    fn reversed(&self) -> NFA {
+        let namer = new_namer();
+        let mut copies: HashMap<usize, State> = HashMap::new();
+        let mut copy_of = |state: &State| {
+            copies
+                .entry(state.borrow().name)
+                .or_insert_with(|| {
+                    let mut copy = _State::new(namer.borrow_mut()(), false);
+                    copy.assertion = state.borrow().assertion;
+                    copy.look = state.borrow().look.clone();
+                    copy.into_cell()
+                })
+                .clone()
+        };
+
+        let mut pending: Vec<State> = vec![self.start.clone()];
+        let mut visited: HashSet<usize> = HashSet::new();
+        while let Some(state) = pending.pop() {
+            let original = state.borrow();
+            if !visited.insert(original.name) {
+                continue;
+            }
+
+            let copy = copy_of(&state);
+            for (class, to) in &original.transitions {
+                copy_of(to)
+                    .borrow_mut()
+                    .add_transition(copy.clone(), class.clone());
+                pending.push(to.clone());
+            }
+            for to in &original.epsilon_transitions {
+                copy_of(to)
+                    .borrow_mut()
+                    .add_epsilon_transition(copy.clone());
+                pending.push(to.clone());
+            }
+        }
+
+        let start = copy_of(&self.end);
+        let end = copy_of(&self.start);
+        end.borrow_mut().is_end = true;
+        NFA::new(start, end)
+    }
 }
 
 impl PartialEq for NFA {
@@ -243,180 +652,167 @@ impl Hash for NFA {
 
 // ----------
 
-pub fn postfix_to_nfa(regex: &str) -> Result<NFA, String> {
+/// Builds the NFA of `ast`, parsed from `pattern`, with Thompson's
+/// construction: each node from the NFAs of its children, see `Ast::fold`.
+pub fn ast_to_nfa(pattern: &str, ast: &Ast) -> Result<NFA, Error> {
     let namer = new_namer();
+    ast.fold(
+        |_| 1,
+        |ast, operands| node_to_nfa(pattern, ast, operands, namer.clone()),
+    )
+}
 
-    if regex.is_empty() {
-        return Ok(NFA::from_epsilon(namer.clone()));
-    }
-
-    let mut stack: Vec<NFA> = Vec::new();
-    for token in regex.chars() {
-        match token {
-            '*' => {
-                if stack.is_empty() {
-                    return Err(
-                        "stack is empty while expecting at least one element for operation: *"
-                            .to_string(),
-                    );
-                }
-                let nfa = stack.pop().unwrap();
-                stack.push(nfa.kleen_closure(namer.clone()))
+/// Builds the NFA of `ast` from `operands`, the NFAs of its children in the
+/// order `Ast::children` gives them.
+fn node_to_nfa(pattern: &str, ast: &Ast, operands: Vec<NFA>, namer: Namer) -> Result<NFA, Error> {
+    let mut operands = operands.into_iter();
+    let nfa = match &ast.kind {
+        AstKind::Empty => NFA::from_epsilon(namer),
+        AstKind::Literal(c) => NFA::from_symbol(namer, *c),
+        AstKind::Class(class) => NFA::from_class(namer, class.clone()),
+        AstKind::Assertion(assertion) => NFA::from_assertion(namer, *assertion),
+        AstKind::Concat(_) => {
+            let mut nfa = operands.next().unwrap();
+            for operand in operands {
+                nfa = nfa.concat(operand);
             }
-            '?' => {
-                if stack.is_empty() {
-                    return Err(
-                        "stack is empty while expecting at least one element for operation: ?"
-                            .to_string(),
-                    );
-                }
-                let nfa = stack.pop().unwrap();
-                stack.push(nfa.zero_or_one(namer.clone()));
+            nfa
+        }
+        AstKind::Alternation(_) => {
+            let mut nfa = operands.next().unwrap();
+            for operand in operands {
+                nfa = nfa.union(namer.clone(), operand);
             }
-            '+' => {
-                if stack.is_empty() {
-                    return Err(
-                        "stack is empty while expecting at least one element for operation: +"
-                            .to_string(),
-                    );
-                }
-                let nfa = stack.pop().unwrap();
-                stack.push(nfa.one_or_more(namer.clone()));
+            nfa
+        }
+        AstKind::Repetition(repetition) => {
+            let nfa = repeat(operands.next().unwrap(), namer.clone(), repetition);
+            match repetition.greed {
+                Greed::Possessive => NFA::from_atomic(namer, nfa),
+                _ => nfa,
             }
-            '|' => {
-                if stack.len() < 2 {
-                    return Err(format!(
-                        "stack has less than two elements, while expecting at least two element \
-                        for operation: `|`, number of elements in stack: {}",
-                        stack.len()
-                    ));
+        }
+        AstKind::Group(group) => {
+            let inner = operands.next().unwrap();
+            match group.kind {
+                GroupKind::Capture(index) => NFA::from_group(namer, index, inner),
+                GroupKind::NonCapture => inner,
+                GroupKind::Atomic => NFA::from_atomic(namer, inner),
+                GroupKind::Look { ahead, negated } => {
+                    NFA::from_look(namer.clone(), look(namer, ahead, negated, inner))
                 }
-                let right = stack.pop().unwrap();
-                let mut left = stack.pop().unwrap();
-                stack.push(left.union(namer.clone(), right));
+                // Its groups can only be called, which only the pda can do.
+                GroupKind::Define => NFA::from_epsilon(namer),
             }
-            '.' => {
-                if stack.len() < 2 {
-                    return Err(format!(
-                        "stack has less than two elements, while expecting at least two element, \
-                        for operation: `.`, number of elements in stack: {}",
-                        stack.len()
-                    ));
+        }
+        AstKind::Backreference {
+            index,
+            case_insensitive,
+        } => NFA::from_backref(namer, *index, *case_insensitive),
+        AstKind::Conditional(conditional) => {
+            let condition = match &conditional.condition {
+                ast::Condition::Group(index) => Condition::Group(*index),
+                // Built as a lookaround, whose start state holds it.
+                ast::Condition::Look(_) => {
+                    let look = operands.next().unwrap().start.borrow_mut().look.take();
+                    Condition::Look(look.unwrap())
                 }
-                let right = stack.pop().unwrap();
-                let mut left = stack.pop().unwrap();
-                stack.push(left.concat(right));
-            }
-            _ => {
-                stack.push(NFA::from_symbol(namer.clone(), token));
-            }
+            };
+            let yes = operands.next().unwrap();
+            NFA::from_conditional(namer, condition, yes, operands.next())
         }
-    }
+        AstKind::Call(_) => {
+            return Err(ast.unsupported(
+                pattern,
+                "recursive patterns need the pda engine, use `pda::compile`",
+            ))
+        }
+        AstKind::Intersection(_) | AstKind::Complement(_) => {
+            return Err(ast.unsupported(pattern, "intersections and complements need `regex0`"))
+        }
+    };
+    Ok(nfa)
+}
 
-    if stack.len() != 1 {
-        Err(format!(
-            "expecting exactly one element in NFA stack, but got: {}",
-            stack.len()
-        ))
-    } else {
-        Ok(stack.pop().unwrap())
+fn repeat(nfa: NFA, namer: Namer, repetition: &Repetition) -> NFA {
+    let greedy = repetition.greed != Greed::Lazy;
+    match (repetition.min, repetition.max) {
+        (0, None) => nfa.kleen_closure(namer, greedy),
+        (1, None) => nfa.one_or_more(namer, greedy),
+        (0, Some(1)) => nfa.zero_or_one(namer, greedy),
+        (min, max) => nfa.repeat(namer, min, max, greedy),
     }
 }
 
-// ----------
+fn look(namer: Namer, ahead: bool, negated: bool, inner: NFA) -> Rc<Look> {
+    let id = namer.borrow_mut()();
+    Rc::new(Look {
+        id,
+        reversed: inner.reversed(),
+        nfa: inner,
+        ahead,
+        negated,
+    })
+}
 
-fn parse_tree_to_nfa(root: &TreeNode, namer: Namer) -> Result<NFA, String> {
-    match root.label.as_str() {
-        "Expr" => {
-            let mut term = parse_tree_to_nfa(&root.children[0], namer.clone())?;
-            match root.children.len() {
-                // Expr -> Term '|' Expr
-                3 => Ok(term.union(
-                    namer.clone(),
-                    parse_tree_to_nfa(&root.children[2], namer.clone())?,
-                )),
-                _ => Ok(term),
-            }
-        }
-        "Term" => {
-            let mut factor = parse_tree_to_nfa(&root.children[0], namer.clone())?;
-            match root.children.len() {
-                2 => Ok(factor.concat(parse_tree_to_nfa(&root.children[1], namer)?)),
-                _ => Ok(factor),
-            }
-        }
-        "Factor" => {
-            let atom = parse_tree_to_nfa(&root.children[0], namer.clone())?;
-            match root.children.len() {
-                2 => match root.children[1].label.as_str() {
-                    "*" => Ok(atom.kleen_closure(namer)),
-                    "+" => Ok(atom.one_or_more(namer)),
-                    "?" => Ok(atom.zero_or_one(namer)),
-                    _ => Ok(atom),
-                },
-                _ => Ok(atom),
-            }
-        }
-        "Atom" => match root.children.len() {
-            3 => parse_tree_to_nfa(&root.children[1], namer),
-            _ => parse_tree_to_nfa(&root.children[0], namer),
-        },
-        "Char" => match root.children.len() {
-            2 => Ok(NFA::from_symbol(
-                namer,
-                root.children[1].label.chars().next().unwrap(),
-            )),
-            _ => Ok(NFA::from_symbol(
-                namer,
-                root.children[0].label.chars().next().unwrap(),
-            )),
-        },
-        _ => Err(format!("unrecognized node label: {}", root.label)),
-    }
-}
-
-pub fn infix_to_nfa(regex: &str) -> Result<NFA, String> {
+pub fn infix_to_nfa(regex: &str) -> Result<NFA, Error> {
+    infix_to_nfa_with_flags(regex, Flags::default())
+}
+
+pub fn infix_to_nfa_with_flags(regex: &str, flags: Flags) -> Result<NFA, Error> {
+    infix_to_nfa_with_options(regex, flags.into())
+}
+
+pub fn infix_to_nfa_with_options(regex: &str, options: Options) -> Result<NFA, Error> {
+    parse_to_nfa(regex, options, false)
+}
+
+/// Builds the NFA of `regex`. With `backtracking` set, this includes the
+/// constructs only the backtracking engine can match, otherwise they are
+/// rejected.
+pub(super) fn parse_to_nfa(
+    regex: &str,
+    options: Options,
+    backtracking: bool,
+) -> Result<NFA, Error> {
     let namer = new_namer();
 
     if regex.is_empty() {
         return Ok(NFA::from_epsilon(namer));
     }
 
-    let parse_tree = Parser::new(regex.to_string()).parse()?;
-    parse_tree_to_nfa(&parse_tree, namer)
+    let mut parser = Parser::with_options(regex.to_string(), options);
+    let ast = parser.parse()?;
+    if let Some(error) = parser.unsupported(backtracking) {
+        return Err(error);
+    }
+    let mut nfa = ast_to_nfa(regex, &ast)?;
+    nfa.group_names = parser.group_names().to_vec();
+
+    Ok(nfa)
 }
 
 // ----------
 
-fn add_next_state(state: &State, next_states: &mut Vec<State>, visited: Rc<RefCell<Vec<usize>>>) {
-    if state.borrow().epsilon_transitions.is_empty() {
-        next_states.push(state.clone());
-    } else {
-        for s in &state.borrow().epsilon_transitions {
-            if !visited.borrow().contains(&s.borrow().name) {
-                visited.borrow_mut().push(s.borrow().name);
-                add_next_state(s, next_states, visited.clone())
-            }
-        }
-    }
-}
-
 pub fn recognize(nfa: &NFA, word: &str) -> bool {
+    let cx = Context::new(word);
     let mut current_states: Vec<State> = vec![];
 
     // The initial set of current states is either the start state or
     // the set of states reachable by epsilon transitions from the start state.
-    add_next_state(
-        &nfa.start,
-        &mut current_states,
-        Rc::new(RefCell::new(vec![])),
-    );
+    add_reachable(&nfa.start, &cx, 0, &mut current_states, &mut HashSet::new());
 
-    for symbol in word.chars() {
+    for (at, symbol) in word.char_indices() {
+        // Shared by the whole step, so that a state reached along several
+        // paths is added once.
+        let mut seen: HashSet<usize> = HashSet::new();
         let mut next_states: Vec<State> = vec![];
         for state in &current_states {
-            if let Some(next_state) = state.borrow().transitions.get(&symbol) {
-                add_next_state(next_state, &mut next_states, Rc::new(RefCell::new(vec![])));
+            for (class, next_state) in &state.borrow().transitions {
+                if class.contains(symbol) {
+                    let to = at + symbol.len_utf8();
+                    add_reachable(next_state, &cx, to, &mut next_states, &mut seen);
+                }
             }
         }
 
@@ -425,3 +821,119 @@ pub fn recognize(nfa: &NFA, word: &str) -> bool {
 
     current_states.into_iter().any(|s| s.borrow().is_end)
 }
+
+// ----------
+
+/// Capture positions of a thread: the start and end of group `i` are at
+/// `2 * i` and `2 * i + 1`.
+pub(super) type Slots = Vec<Option<usize>>;
+
+/// Threads of a Pike VM simulation, in priority order, each carrying its
+/// capture positions.
+struct Threads {
+    list: Vec<(State, Slots)>,
+    seen: HashSet<usize>,
+}
+
+impl Threads {
+    fn new() -> Threads {
+        Threads {
+            list: vec![],
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Follows epsilon transitions depth first in their insertion order, so
+    /// threads are added in the order a backtracking matcher would try them.
+    fn add(&mut self, state: &State, slots: Slots, cx: &Context, at: usize) {
+        let mut stack = vec![(state.clone(), slots)];
+        while let Some((state, mut slots)) = stack.pop() {
+            if !self.seen.insert(state.borrow().name) {
+                continue;
+            }
+
+            let s = state.borrow();
+            if !s.passable(cx, at) {
+                continue;
+            }
+            if let Some(slot) = s.slot {
+                slots[slot] = Some(at);
+            }
+            if s.is_end || !s.transitions.is_empty() {
+                self.list.push((state.clone(), slots.clone()));
+            }
+            for to in s.epsilon_transitions.iter().rev() {
+                stack.push((to.clone(), slots.clone()));
+            }
+        }
+    }
+}
+
+/// Searches `haystack` for the leftmost match, returning its byte span. Among
+/// matches starting at the same position the one preferred by the quantifiers
+/// wins: greedy ones take as much as they can, lazy ones as little.
+pub fn find(nfa: &NFA, haystack: &str) -> Option<(usize, usize)> {
+    captures(nfa, haystack).and_then(|groups| groups[0])
+}
+
+/// Like `find`, but also returns the span of every capture group, indexed as in
+/// `NFA::group_names`. Groups that did not participate in the match are `None`.
+pub fn captures(nfa: &NFA, haystack: &str) -> Option<Vec<Option<(usize, usize)>>> {
+    let slot_count = 2 * nfa.captures_len();
+    let cx = Context::new(haystack);
+    let mut current = Threads::new();
+    let mut matched: Option<Slots> = None;
+
+    let positions = haystack
+        .char_indices()
+        .map(|(at, c)| (at, Some(c)))
+        .chain(std::iter::once((haystack.len(), None)));
+
+    for (at, symbol) in positions {
+        // A new attempt starting here has the lowest priority of all.
+        if matched.is_none() {
+            let mut slots = vec![None; slot_count];
+            slots[0] = Some(at);
+            current.add(&nfa.start, slots, &cx, at);
+        }
+
+        let mut next = Threads::new();
+        for (state, slots) in &current.list {
+            let state = state.borrow();
+            if state.is_end {
+                // Lower priority threads can no longer win.
+                let mut slots = slots.clone();
+                slots[1] = Some(at);
+                matched = Some(slots);
+                break;
+            }
+
+            if let Some(symbol) = symbol {
+                let after_at = at + symbol.len_utf8();
+                for (class, to) in &state.transitions {
+                    if class.contains(symbol) {
+                        next.add(to, slots.clone(), &cx, after_at);
+                    }
+                }
+            }
+        }
+
+        if next.list.is_empty() && matched.is_some() {
+            break;
+        }
+        current = next;
+    }
+
+    matched.map(|slots| spans(&slots))
+}
+
+/// Pairs up capture slots into the span of each group.
+pub(super) fn spans(slots: &Slots) -> Vec<Option<(usize, usize)>> {
+    slots
+        .chunks(2)
+        .map(|span| match span {
+            [Some(start), Some(end)] => Some((*start, *end)),
+            _ => None,
+        })
+        .collect()
+}
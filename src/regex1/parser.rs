@@ -1,210 +1,1329 @@
-use std::collections::HashMap;
+use std::ops::Range;
 
-pub fn with_explicit_concat(str: &str) -> String {
-    let mut output = String::with_capacity((str.len() as f32 * 1.5) as usize);
+use crate::assertion::Assertion;
+use crate::ast::{Ast, AstKind, Condition, Conditional, Greed, Group, GroupKind, Repetition};
+use crate::class::CharClass;
+use crate::error::{Error, ErrorKind};
+use crate::flags::Flags;
+use crate::options::Options;
+use crate::syntax::{parse_escape, parse_repeat, Repeat, Syntax, MAX_EXPANSION, MAX_NESTING_LIMIT};
 
-    for (i, token) in str.chars().enumerate() {
-        output.push(token);
+//  Recursive descent parser for regular expressions, giving the `Ast` of every
+//  front end. Implements the following grammar:
+//
+//  Expr -> Branch ('|' Branch)*
+//  Branch -> Term | ''
+//  Term -> Factor+
+//  Factor -> Atom | Atom Quantifier | Atom Quantifier '?' | Atom Quantifier '+'
+//  Quantifier -> MetaChar | Repeat
+//  Atom -> Char | Class | Shorthand | Assertion | Backref | '.' | Group
+//  Group -> '(' Expr ')' | '(?:' Expr ')' | '(?P<' Name '>' Expr ')' | '(?<' Name '>' Expr ')'
+//         | '(?' FlagSet ')' | '(?' FlagSet ':' Expr ')' | '(?>' Expr ')' | Look
+//         | '(?(' Condition ')' Branch ')' | '(?(' Condition ')' Branch '|' Branch ')'
+//         | '(?(DEFINE)' Branch ')' | Call
+//  Condition -> Digits | '<' Name '>' | Look
+//  Call -> '(?R)' | '(?' Digits ')' | '(?&' Name ')' | '(?P>' Name ')'
+//  Look -> '(?=' Expr ')' | '(?!' Expr ')' | '(?<=' Expr ')' | '(?<!' Expr ')'
+//  FlagSet -> FlagLetter* | FlagLetter* '-' FlagLetter*
+//  FlagLetter -> 'i' | 'm' | 's' | 'x' | 'u'
+//  Assertion -> '^' | '$' | '\A' | '\z' | '\b' | '\B'
+//  Backref -> '\' NonZeroDigit Digit* | '\k<' Name '>'
+//  Char -> AnyCharExceptMeta | Escape
+//  Escape -> '\' ('n' | 't' | 'r' | 'f' | 'v' | 'a' | 'e') | '\x' Hex Hex | '\x{' Hex+ '}'
+//          | '\u' Hex Hex Hex Hex | '\u{' Hex+ '}' | '\0' Octal? Octal? | '\o{' Octal+ '}'
+//          | '\' AnyCharExceptAsciiAlphanumeric
+//  MetaChar -> '?' | '*' | '+'
+//  Repeat -> '{' Digits '}' | '{' Digits ',' '}' | '{' Digits ',' Digits '}'
+//  Class -> '[' '^'? ClassSet ']'
+//  ClassSet -> ClassUnion | ClassUnion SetOp ClassSet
+//  SetOp -> '&&' | '--' | '~~'
+//  ClassUnion -> ClassItem+
+//  ClassItem -> ClassChar | ClassChar '-' ClassChar | Shorthand | Posix | Class
+//  Posix -> '[:' '^'? Name ':]'
+//  ClassChar -> AnyCharExcept(']', '\') | Escape
+//  Shorthand -> '\d' | '\D' | '\w' | '\W' | '\s' | '\S' | Property
+//  Property -> ('\p' | '\P') (Letter | '{' Name '}')
+//
+//  This is the `Extended` syntax. The `Boolean` syntax adds an intersection,
+//  binding looser than concatenation, and a complement, binding looser than
+//  quantifiers:
+//
+//  Branch -> Operand ('&' Operand)*
+//  Operand -> Term | ''
+//  Factor -> '~' Factor | ...
+//
+//  The `Basic` syntax keeps only this of Atom and Factor:
+//
+//  Factor -> Atom MetaChar*
+//  Atom -> Char | '(' Expr ')'
+//
+//  An empty alternative, operand, group or pattern is an Empty node. A Term of
+//  a single Factor is that Factor, and an Expr of a single Branch that Branch.
+//
+//  Groups and classes are parsed with an explicit stack, so the parser itself
+//  has no recursion to overflow. How deeply they may nest is still limited,
+//  see `Parser::set_nesting_limit`, since lookarounds and atomic groups are
+//  matched recursively. Stacked quantifiers count towards the limit too.
+//
+//  Group names are kept by the parser, see `Parser::group_names`. Groups
+//  inside a lookaround are numbered as usual but never report a span.
+//
+//  '(?#' ... ')' is a comment and may appear between any two Factors or
+//  before a Quantifier. Under the verbose flag unescaped whitespace and '#'
+//  comments running to the end of the line may appear there too; whitespace
+//  inside a Class is still literal.
+//
+//  Named backreferences must follow the group they name; numbered ones may
+//  refer to any group of the pattern. Calls may refer to any group.
+//
+//  '(?flags)' changes the flags until the end of the enclosing group and is an
+//  Empty node; '(?flags:' Expr ')' changes them for Expr only. Nodes come with
+//  the flags in effect where they are written already applied, see `ast`.
+//
+//  A '{' that does not open a Repeat is a literal.
+//
+//  Set operators (intersection, difference, symmetric difference) are left
+//  associative and all bind looser than union, so `[a-z&&[^aeiou]x]` is `[a-z]`
+//  intersected with `[^aeiou]` plus `x`.
+//
+//
 
-        if token != '(' && token != '|' && i < str.len() - 1 {
-            match str.chars().nth(i + 1).unwrap() {
-                '*' | '?' | '+' | '|' | ')' => {}
-                _ => output.push('.'),
-            }
+const fn is_meta_char(c: char) -> bool {
+    matches!(c, '*' | '+' | '?')
+}
+
+/// Suggests escaping `c`, for a metacharacter found where it cannot be one.
+fn escape_help(c: char) -> String {
+    format!("did you mean to escape `{}`? write `\\{}`", c, c)
+}
+
+/// Follows a quantifier to make it lazy (`?`) or possessive (`+`).
+const fn is_quantifier_modifier(c: char) -> bool {
+    matches!(c, '?' | '+')
+}
+
+/// `asts` joined into one node by `join`: an Empty node at `at` if there are
+/// none, and the only one if there is one.
+fn join(mut asts: Vec<Ast>, join: fn(Vec<Ast>) -> AstKind, at: usize) -> Ast {
+    match asts.len() {
+        0 => Ast::new(AstKind::Empty, at..at),
+        1 => asts.pop().unwrap(),
+        _ => {
+            let span = asts[0].span.start..asts[asts.len() - 1].span.end;
+            Ast::new(join(asts), span)
         }
     }
+}
 
-    output
+// A group being parsed, see `Parser::expression`.
+struct Frame {
+    kind: FrameKind,
+    start: usize,
+    // The flags in effect outside the group, restored once it is closed.
+    outer_flags: Flags,
+    // The alternatives read so far, the operands of the current one, then the
+    // factors of the current operand.
+    branches: Vec<Ast>,
+    operands: Vec<Ast>,
+    factors: Vec<Ast>,
+    // Where the `~` waiting for the next factor are.
+    complements: Vec<usize>,
+    // How many atoms the group holds once its counted repetitions are
+    // expanded, see `MAX_EXPANSION`.
+    size: usize,
 }
 
-pub fn to_postfix(str: &str) -> String {
-    let precedence: HashMap<char, usize> = vec![('|', 0), ('.', 1), ('?', 2), ('*', 2), ('+', 2)]
-        .into_iter()
-        .collect();
-    let mut output = String::with_capacity(str.len());
-    let mut operator_stack: Vec<char> = vec![];
+enum FrameKind {
+    /// The whole pattern.
+    Pattern,
+    Capture(usize),
+    NonCapture,
+    Atomic,
+    /// A lookaround and, if it is the condition of a conditional, where that
+    /// starts.
+    Look {
+        ahead: bool,
+        negated: bool,
+        conditional: Option<usize>,
+    },
+    Conditional(Condition),
+    Define,
+}
 
-    for token in str.chars() {
-        match token {
-            '.' | '|' | '*' | '?' | '+' => {
-                while !operator_stack.is_empty()
-                    && *operator_stack.last().unwrap() != '('
-                    && precedence[operator_stack.last().unwrap()] >= precedence[&token]
-                {
-                    output.push(operator_stack.pop().unwrap());
-                }
-                operator_stack.push(token);
-            }
-            '(' => {
-                operator_stack.push('(');
-            }
-            ')' => {
-                while !operator_stack.is_empty() && *operator_stack.last().unwrap() != '(' {
-                    output.push(operator_stack.pop().unwrap());
-                }
-                operator_stack.pop();
-            }
-            _ => {
-                output.push(token);
-            }
+impl Frame {
+    fn new(kind: FrameKind, start: usize, outer_flags: Flags) -> Frame {
+        Frame {
+            kind,
+            start,
+            outer_flags,
+            branches: vec![],
+            operands: vec![],
+            factors: vec![],
+            complements: vec![],
+            size: 0,
         }
     }
 
-    while !operator_stack.is_empty() {
-        output.push(operator_stack.pop().unwrap());
+    // Adds a factor of `size` atoms to the current operand, under the `~`
+    // before it.
+    fn push(&mut self, mut factor: Ast, size: usize) {
+        self.size = self.size.saturating_add(size);
+        while let Some(start) = self.complements.pop() {
+            let span = start..factor.span.end;
+            factor = Ast::new(AstKind::Complement(Box::new(factor)), span);
+        }
+        self.factors.push(factor);
     }
 
-    output
-}
-
-//  Recursive descent parser for regular expressions. Implements the following grammar:
-//
-//  Expr -> Term | Term '|' Expr
-//  Term -> Factor | Factor Term
-//  Factor -> Atom | Atom MetaChar
-//  Atom -> Char | '(' Expr ')'
-//  Char -> AnyCharExceptMeta | '\' AnyChar
-//  MetaChar -> '?' | '*' | '+'
-//
-//
+    // Ends the current operand at `at`.
+    fn end_operand(&mut self, at: usize) {
+        let factors = std::mem::take(&mut self.factors);
+        self.operands.push(join(factors, AstKind::Concat, at));
+    }
 
-pub struct TreeNode {
-    pub label: String,
-    pub children: Vec<TreeNode>,
-}
+    // Ends the current alternative at `at`.
+    fn end_branch(&mut self, at: usize) {
+        self.end_operand(at);
+        let operands = std::mem::take(&mut self.operands);
+        self.branches
+            .push(join(operands, AstKind::Intersection, at));
+    }
 
-impl TreeNode {
-    fn new(label: String, children: Vec<TreeNode>) -> TreeNode {
-        TreeNode { label, children }
+    fn expr(self) -> Ast {
+        join(self.branches, AstKind::Alternation, self.start)
     }
 
-    fn from_label(label: char) -> TreeNode {
-        TreeNode {
-            label: String::from(label),
-            children: vec![],
+    // The node of the group, closed at `end`.
+    fn close(self, end: usize) -> Ast {
+        let span = self.start..end;
+        let group = |kind, ast| {
+            let group = Group {
+                kind,
+                ast: Box::new(ast),
+            };
+            Ast::new(AstKind::Group(group), span.clone())
+        };
+        match self.kind {
+            FrameKind::Pattern => self.expr(),
+            FrameKind::Capture(index) => group(GroupKind::Capture(index), self.expr()),
+            FrameKind::NonCapture => group(GroupKind::NonCapture, self.expr()),
+            FrameKind::Atomic => group(GroupKind::Atomic, self.expr()),
+            FrameKind::Look { ahead, negated, .. } => {
+                group(GroupKind::Look { ahead, negated }, self.expr())
+            }
+            FrameKind::Conditional(condition) => {
+                let mut branches = self.branches.into_iter().map(Box::new);
+                let conditional = Conditional {
+                    condition,
+                    yes: branches.next().unwrap(),
+                    no: branches.next(),
+                };
+                Ast::new(AstKind::Conditional(conditional), span)
+            }
+            FrameKind::Define => {
+                let branch = self.branches.into_iter().next().unwrap();
+                group(GroupKind::Define, branch)
+            }
         }
     }
 }
 
-const fn is_meta_char(c: char) -> bool {
-    matches!(c, '*' | '+' | '?')
+// A class being parsed, see `Parser::class_set`.
+struct ClassFrame {
+    start: usize,
+    negated: bool,
+    // The class left of the set operator read last, if any, with the operator.
+    left: Option<(CharClass, char)>,
+    // The union of the items read since, and how many there are.
+    union: CharClass,
+    items: usize,
 }
 
 #[derive(Debug)]
 pub struct Parser {
     pattern: String,
+    // The pattern scanned by char, which `position` indexes.
+    chars: Vec<char>,
     position: usize,
     parsed: bool,
+    flags: Flags,
+    syntax: Syntax,
+    group_names: Vec<Option<String>>,
+    // How many groups and classes are open, and how many may be.
+    depth: usize,
+    nesting_limit: usize,
+    // Group indexes referred to by backreferences and conditionals, checked
+    // once every group is known, with where they are written.
+    backrefs: Vec<(usize, Range<usize>)>,
+    conditions: Vec<(usize, Range<usize>)>,
+    // Targets of subroutine calls, resolved once every group is known.
+    calls: Vec<(String, Range<usize>)>,
+    // The first construct found that only the backtracking engine supports.
+    backtracking: Option<(&'static str, Range<usize>)>,
+    // Problems the parser went on after, reported once the pattern is parsed.
+    errors: Vec<Error>,
 }
 
 impl Parser {
     pub fn new(pattern: String) -> Parser {
+        Self::with_flags(pattern, Flags::default())
+    }
+
+    pub fn with_flags(pattern: String, flags: Flags) -> Parser {
+        Self::with_options(pattern, flags.into())
+    }
+
+    pub fn with_options(pattern: String, options: Options) -> Parser {
         Parser {
+            chars: pattern.chars().collect(),
             pattern,
             position: 0,
             parsed: false,
+            flags: options.flags,
+            syntax: Syntax::default(),
+            group_names: vec![None],
+            depth: 0,
+            nesting_limit: options.nesting_limit.min(MAX_NESTING_LIMIT),
+            backrefs: vec![],
+            conditions: vec![],
+            calls: vec![],
+            backtracking: None,
+            errors: vec![],
         }
     }
 
-    pub fn parse(&mut self) -> Result<TreeNode, String> {
+    /// Sets the syntax of the pattern, `Syntax::Extended` by default.
+    pub fn set_syntax(&mut self, syntax: Syntax) {
+        self.syntax = syntax;
+    }
+
+    /// Sets how deeply groups and classes may nest, `NESTING_LIMIT` unless
+    /// set in the options, and at most `MAX_NESTING_LIMIT`. Deeper patterns
+    /// are rejected with `ErrorKind::NestingTooDeep`.
+    pub fn set_nesting_limit(&mut self, limit: usize) {
+        self.nesting_limit = limit.min(MAX_NESTING_LIMIT);
+    }
+
+    /// Names of the capture groups by index, group 0 being the whole match.
+    /// Complete once the pattern is parsed.
+    pub fn group_names(&self) -> &[Option<String>] {
+        &self.group_names
+    }
+
+    /// Names the first construct in the pattern that only the backtracking
+    /// engine supports, such as backreferences. Complete once the pattern is
+    /// parsed.
+    pub fn needs_backtracking(&self) -> Option<&'static str> {
+        self.backtracking.as_ref().map(|(feature, _)| *feature)
+    }
+
+    /// Whether the pattern calls groups as subroutines, which only the pda
+    /// supports. Complete once the pattern is parsed.
+    pub fn is_recursive(&self) -> bool {
+        !self.calls.is_empty()
+    }
+
+    /// The error for a parsed pattern that an engine cannot match: an
+    /// automaton if `backtracking` is false, the backtracking engine otherwise.
+    pub(crate) fn unsupported(&self, backtracking: bool) -> Option<Error> {
+        if let (false, Some((feature, span))) = (backtracking, &self.backtracking) {
+            let reason = format!(
+                "{} cannot be matched by an automaton, use `regex1::backtrack`",
+                feature
+            );
+            return Some(Error::new(
+                ErrorKind::Unsupported(reason),
+                &self.pattern,
+                span.clone(),
+            ));
+        }
+
+        self.calls.first().map(|(_, span)| {
+            let reason = "recursive patterns need the pda engine, use `pda::compile`";
+            Error::new(
+                ErrorKind::Unsupported(reason.to_string()),
+                &self.pattern,
+                span.clone(),
+            )
+        })
+    }
+
+    /// Parses the pattern, returning the first error in it if any.
+    pub fn parse(&mut self) -> Result<Ast, Error> {
+        self.parse_all()
+            .map_err(|errors| errors.into_iter().next().unwrap())
+    }
+
+    /// Parses the pattern, returning every error in it if any, ordered by
+    /// position. The parser goes on after unbalanced parentheses, quantifiers
+    /// with nothing to repeat, malformed escapes and invalid class ranges;
+    /// other errors end the parse.
+    pub fn parse_all(&mut self) -> Result<Ast, Vec<Error>> {
         if self.parsed {
-            return Err("already parsed".to_string());
+            return Err(vec![Error::whole(ErrorKind::AlreadyParsed, &self.pattern)]);
         }
 
         self.parsed = true;
-        self.expression()
+        let result = self.pattern_();
+        let mut errors = std::mem::take(&mut self.errors);
+        match result {
+            Ok(ast) if errors.is_empty() => Ok(ast),
+            result => {
+                errors.extend(result.err());
+                errors.sort_by_key(|error| error.char_span().start);
+                Err(errors)
+            }
+        }
+    }
+
+    fn pattern_(&mut self) -> Result<Ast, Error> {
+        let mut ast = self.expression()?;
+        self.resolve_calls(&mut ast);
+
+        let groups = self.group_names.len();
+        for (index, span) in &self.backrefs {
+            if *index >= groups {
+                let kind = ErrorKind::UndefinedBackreference(format!("\\{}", index));
+                self.errors
+                    .push(Error::new(kind, &self.pattern, span.clone()));
+            }
+        }
+        for (index, span) in &self.conditions {
+            if *index >= groups {
+                let kind = ErrorKind::UndefinedCondition(index.to_string());
+                self.errors
+                    .push(Error::new(kind, &self.pattern, span.clone()));
+            }
+        }
+        Ok(ast)
+    }
+
+    // Parses the whole pattern. The groups still open are kept on an explicit
+    // stack rather than on the call stack, so that neither long nor deeply
+    // nested patterns can overflow it.
+    fn expression(&mut self) -> Result<Ast, Error> {
+        let mut stack = vec![Frame::new(FrameKind::Pattern, 0, self.flags)];
+        let boolean = self.syntax == Syntax::Boolean;
+
+        loop {
+            self.skip_trivia()?;
+            let c = self.peek_nth(0);
+            // A `~` needs an operand.
+            if matches!(c, None | Some('|' | '&' | ')'))
+                && !stack.last().unwrap().complements.is_empty()
+            {
+                return Err(self.error(ErrorKind::MissingOperand));
+            }
+
+            match c {
+                Some('|') => {
+                    let frame = stack.last_mut().unwrap();
+                    match frame.kind {
+                        FrameKind::Conditional(_) if !frame.branches.is_empty() => {
+                            return Err(self.error(ErrorKind::TooManyBranches));
+                        }
+                        FrameKind::Define => return Err(self.error(ErrorKind::DefineAlternatives)),
+                        _ => {}
+                    }
+                    frame.end_branch(self.position);
+                    self.match_('|')?;
+                }
+                Some('&') if boolean => {
+                    stack.last_mut().unwrap().end_operand(self.position);
+                    self.match_('&')?;
+                }
+                Some('~') if boolean => {
+                    self.enter(self.position)?;
+                    stack.last_mut().unwrap().complements.push(self.position);
+                    self.match_('~')?;
+                }
+                // A ')' closing no group is skipped.
+                Some(')') if stack.len() == 1 => {
+                    let error = self
+                        .error(ErrorKind::UnbalancedParenthesis)
+                        .with_help(escape_help(')'));
+                    self.errors.push(error);
+                    self.position += 1;
+                }
+                None | Some(')') => {
+                    let mut frame = stack.pop().unwrap();
+                    frame.end_branch(self.position);
+                    if let FrameKind::Pattern = frame.kind {
+                        return Ok(frame.close(self.position));
+                    }
+
+                    self.close_group(frame.start)?;
+                    self.flags = frame.outer_flags;
+                    if let FrameKind::Look {
+                        conditional: Some(start),
+                        ..
+                    } = frame.kind
+                    {
+                        // The lookaround is the condition of a conditional,
+                        // whose branches come next. It takes the place of the
+                        // lookaround towards the nesting limit.
+                        let size = frame.size;
+                        let look = frame.close(self.position);
+                        self.backtracking
+                            .get_or_insert(("conditionals", start..self.position));
+                        let kind = FrameKind::Conditional(Condition::Look(Box::new(look)));
+                        let mut conditional = Frame::new(kind, start, self.flags);
+                        conditional.size = size;
+                        stack.push(conditional);
+                        continue;
+                    }
+                    self.depth -= 1;
+
+                    let size = frame.size.max(1);
+                    let group = frame.close(self.position);
+                    self.push_factor(&mut stack, group, size)?;
+                }
+                Some('(') => {
+                    if let Some(ast) = self.open_group(&mut stack)? {
+                        self.push_factor(&mut stack, ast, 1)?;
+                    }
+                }
+                Some(_) => {
+                    let atom = self.atom()?;
+                    self.push_factor(&mut stack, atom, 1)?;
+                }
+            }
+        }
+    }
+
+    // Adds `atom` of `size` atoms, with the quantifiers following it, to the
+    // group on top of `stack`.
+    fn push_factor(&mut self, stack: &mut [Frame], atom: Ast, size: usize) -> Result<(), Error> {
+        let (factor, size) = self.factor(atom, size)?;
+        let frame = stack.last_mut().unwrap();
+        self.depth -= frame.complements.len();
+        frame.push(factor, size);
+        Ok(())
+    }
+
+    // Parses the quantifiers following `atom` of `size` atoms, if any: one,
+    // possibly lazy or possessive, or any number of stacked ones in the `Basic`
+    // syntax. Returns the factor with its size once expanded.
+    fn factor(&mut self, atom: Ast, size: usize) -> Result<(Ast, usize), Error> {
+        let start = atom.span.start;
+        let depth = self.depth;
+        let mut ast = atom;
+        let mut size = size;
+
+        loop {
+            self.skip_trivia()?;
+            let (min, max) = if self.has_more_chars() && is_meta_char(self.peek()) {
+                match self.next()? {
+                    '*' => (0, None),
+                    '+' => (1, None),
+                    _ => (0, Some(1)),
+                }
+            } else if let Some(repeat) = self.repeat() {
+                let repeat_start = self.position;
+                self.position += repeat.len;
+                repeat
+                    .validate()
+                    .map_err(|kind| self.error_from(repeat_start, kind))?;
+                (repeat.min, repeat.max)
+            } else {
+                break;
+            };
+
+            // Each stacked quantifier nests the tree one level deeper.
+            if let AstKind::Repetition(_) = ast.kind {
+                self.enter(start)?;
+            }
+
+            let mut greed = Greed::Greedy;
+            if self.syntax != Syntax::Basic
+                && self.has_more_chars()
+                && is_quantifier_modifier(self.peek())
+            {
+                if self.next()? == '+' {
+                    self.backtracking.get_or_insert((
+                        "atomic groups and possessive quantifiers",
+                        self.position - 1..self.position,
+                    ));
+                    greed = Greed::Possessive;
+                } else {
+                    greed = Greed::Lazy;
+                }
+            }
+
+            // The engines copy the operand once per count, and once more for
+            // an unbounded maximum.
+            let copies = max.unwrap_or(min + 1).max(1);
+            size = size.saturating_mul(copies);
+            if size > MAX_EXPANSION {
+                return Err(self.error_from(start, ErrorKind::ExpansionTooLarge));
+            }
+
+            let repetition = Repetition {
+                min,
+                max,
+                greed,
+                ast: Box::new(ast),
+            };
+            ast = Ast::new(AstKind::Repetition(repetition), start..self.position);
+            if self.syntax != Syntax::Basic {
+                break;
+            }
+        }
+
+        self.depth = depth;
+        Ok((ast, size))
     }
 
-    fn expression(&mut self) -> Result<TreeNode, String> {
-        let term = self.term()?;
+    // Any atom but a group, which `expression` handles.
+    fn atom(&mut self) -> Result<Ast, Error> {
+        if self.syntax == Syntax::Basic {
+            return self.char_();
+        }
 
-        let children = if self.has_more_chars() && self.peek() == '|' {
-            self.match_('|')?;
-            let expr = self.expression()?;
-            vec![term, TreeNode::from_label('|'), expr]
+        let start = self.position;
+        let kind = if self.peek() == '[' {
+            AstKind::Class(self.class_set()?)
+        } else if self.peek() == '.' {
+            self.match_('.')?;
+            AstKind::Class(self.flags.dot_class())
+        } else if let Some(class) = self.shorthand()? {
+            AstKind::Class(class)
+        } else if let Some(assertion) = self.assertion()? {
+            AstKind::Assertion(assertion)
+        } else if let Some(index) = self.backref()? {
+            AstKind::Backreference {
+                index,
+                case_insensitive: self.flags.case_insensitive,
+            }
         } else {
-            vec![term]
+            return self.char_();
         };
-
-        Ok(TreeNode::new("Expr".to_string(), children))
+        Ok(Ast::new(kind, start..self.position))
     }
 
-    fn term(&mut self) -> Result<TreeNode, String> {
-        let factor = self.factor()?;
+    // Parses the opening of a group. A group with contents is pushed on
+    // `stack`, for `expression` to fill and close; one complete already, a
+    // call or a change of flags, is returned.
+    fn open_group(&mut self, stack: &mut Vec<Frame>) -> Result<Option<Ast>, Error> {
+        let start = self.position;
+        let outer_flags = self.flags;
+        self.match_('(')?;
 
-        let children = if self.has_more_chars() && self.peek() != ')' && self.peek() != '|' {
-            vec![factor, self.term()?]
+        let extended = self.syntax != Syntax::Basic;
+        let kind = if extended && self.has_more_chars() && self.peek() == '?' {
+            self.match_('?')?;
+            match (self.peek_nth(0), self.peek_nth(1)) {
+                (Some(':'), _) => {
+                    self.match_(':')?;
+                    FrameKind::NonCapture
+                }
+                (Some('>'), _) => {
+                    self.match_('>')?;
+                    self.backtracking.get_or_insert((
+                        "atomic groups and possessive quantifiers",
+                        start..self.position,
+                    ));
+                    FrameKind::Atomic
+                }
+                (Some(kind @ ('=' | '!')), _) => {
+                    self.next()?;
+                    FrameKind::Look {
+                        ahead: true,
+                        negated: kind == '!',
+                        conditional: None,
+                    }
+                }
+                (Some('<'), Some(kind @ ('=' | '!'))) => {
+                    self.match_('<')?;
+                    self.next()?;
+                    FrameKind::Look {
+                        ahead: false,
+                        negated: kind == '!',
+                        conditional: None,
+                    }
+                }
+                (Some('('), _) => return self.open_conditional(start, stack),
+                (Some('R' | '&' | '0'..='9'), _) | (Some('P'), Some('>')) => {
+                    return self.call().map(Some)
+                }
+                (Some('P'), _) | (Some('<'), _) => {
+                    if self.peek() == 'P' {
+                        self.match_('P')?;
+                    }
+                    let name_start = self.position;
+                    let name = self.group_name()?;
+                    FrameKind::Capture(
+                        self.add_group(Some(name))
+                            .map_err(|kind| self.error_from(name_start, kind))?,
+                    )
+                }
+                (Some(_), _) => {
+                    let flags = self.inline_flags()?;
+                    if self.has_more_chars() && self.peek() == ')' {
+                        // `(?flags)` holds until the end of the enclosing group.
+                        self.match_(')')?;
+                        self.flags = flags;
+                        return Ok(Some(Ast::new(AstKind::Empty, start..self.position)));
+                    }
+                    self.match_(':')?;
+                    self.flags = flags;
+                    FrameKind::NonCapture
+                }
+                (None, _) => return Err(self.error(ErrorKind::UnexpectedEnd)),
+            }
         } else {
-            vec![factor]
+            FrameKind::Capture(self.add_group(None).map_err(|kind| self.error(kind))?)
         };
 
-        Ok(TreeNode::new("Term".to_string(), children))
+        self.enter(start)?;
+        stack.push(Frame::new(kind, start, outer_flags));
+        Ok(None)
     }
 
-    fn factor(&mut self) -> Result<TreeNode, String> {
-        let atom = self.atom()?;
+    // Parses the opening of a conditional group, after its '(?'. A lookaround
+    // condition is pushed on `stack` as a group of its own.
+    fn open_conditional(
+        &mut self,
+        start: usize,
+        stack: &mut Vec<Frame>,
+    ) -> Result<Option<Ast>, Error> {
+        let define: Vec<char> = "(DEFINE)".chars().collect();
+        if self.chars[self.position..].starts_with(&define) {
+            self.position += define.len();
+            self.enter(start)?;
+            stack.push(Frame::new(FrameKind::Define, start, self.flags));
+            return Ok(None);
+        }
 
-        let children = if self.has_more_chars() && is_meta_char(self.peek()) {
-            vec![atom, TreeNode::from_label(self.next()?)]
+        let condition_start = self.position;
+        if self.peek_nth(1) == Some('?') {
+            let (ahead, kind) = match (self.peek_nth(2), self.peek_nth(3)) {
+                (Some(kind @ ('=' | '!')), _) => (true, kind),
+                (Some('<'), Some(kind @ ('=' | '!'))) => (false, kind),
+                _ => return Err(self.error_from(condition_start, ErrorKind::InvalidCondition)),
+            };
+            self.position += if ahead { 3 } else { 4 };
+            self.enter(start)?;
+            let kind = FrameKind::Look {
+                ahead,
+                negated: kind == '!',
+                conditional: Some(start),
+            };
+            stack.push(Frame::new(kind, condition_start, self.flags));
+            return Ok(None);
+        }
+
+        self.match_('(')?;
+        let index = if self.has_more_chars() && self.peek() == '<' {
+            let name = self.group_name()?;
+            self.group_names
+                .iter()
+                .position(|n| n.as_ref() == Some(&name))
+                .ok_or_else(|| {
+                    self.error_from(condition_start, ErrorKind::UndefinedCondition(name))
+                })?
         } else {
-            vec![atom]
+            let mut digits = String::new();
+            while self.has_more_chars() && self.peek().is_ascii_digit() {
+                digits.push(self.next()?);
+            }
+            // Group 0, the whole match, has not participated before it ends.
+            let index = digits
+                .parse::<usize>()
+                .ok()
+                .filter(|&index| index != 0)
+                .ok_or_else(|| self.error_from(condition_start + 1, ErrorKind::InvalidCondition))?;
+            self.conditions
+                .push((index, condition_start + 1..self.position));
+            index
         };
+        self.match_(')')?;
+        self.backtracking
+            .get_or_insert(("conditionals", start..self.position));
 
-        Ok(TreeNode::new("Factor".to_string(), children))
+        self.enter(start)?;
+        stack.push(Frame::new(
+            FrameKind::Conditional(Condition::Group(index)),
+            start,
+            self.flags,
+        ));
+        Ok(None)
     }
 
-    fn atom(&mut self) -> Result<TreeNode, String> {
-        let children = if self.peek() == '(' {
-            self.match_('(')?;
-            let expr = self.expression()?;
-            self.match_(')')?;
-            vec![TreeNode::from_label('('), expr, TreeNode::from_label(')')]
-        } else {
-            vec![self.char_()?]
+    // Parses the rest of a subroutine call, after its '(?'. Targets are
+    // resolved once the whole pattern is parsed, see `resolve_calls`; until
+    // then a Call node holds the index of its target in `calls`.
+    fn call(&mut self) -> Result<Ast, Error> {
+        let start = self.position - 2;
+        let target = match self.next()? {
+            'R' => {
+                self.close_group(start)?;
+                "0".to_string()
+            }
+            '&' => self.name_until(')')?,
+            'P' => {
+                self.match_('>')?;
+                self.name_until(')')?
+            }
+            digit => {
+                let mut digits = digit.to_string();
+                while self.has_more_chars() && self.peek().is_ascii_digit() {
+                    digits.push(self.next()?);
+                }
+                self.close_group(start)?;
+                digits
+            }
         };
-        Ok(TreeNode::new("Atom".to_string(), children))
+        self.calls.push((target, start..self.position));
+
+        let call = AstKind::Call(self.calls.len() - 1);
+        Ok(Ast::new(call, start..self.position))
     }
 
-    fn char_(&mut self) -> Result<TreeNode, String> {
-        if is_meta_char(self.peek()) {
-            return Err(format!("unexpected meta char={}", self.peek()));
+    // Checks the targets of the calls, then replaces the indexes in Call nodes
+    // by those of the groups they call.
+    fn resolve_calls(&mut self, ast: &mut Ast) {
+        let mut targets = vec![];
+        for (target, span) in &self.calls {
+            match self.call_index(target) {
+                Some(index) => targets.push(index),
+                None => {
+                    let kind = ErrorKind::UndefinedCall(target.clone());
+                    self.errors
+                        .push(Error::new(kind, &self.pattern, span.clone()));
+                }
+            }
+        }
+        if targets.len() < self.calls.len() {
+            return;
         }
 
-        let children = if self.peek() == '\\' {
+        // Iterative, as the tree may be as long as the pattern.
+        let mut pending = vec![ast];
+        while let Some(ast) = pending.pop() {
+            if let AstKind::Call(call) = &mut ast.kind {
+                *call = targets[*call];
+            }
+            pending.extend(ast.children_mut());
+        }
+    }
+
+    fn call_index(&self, target: &str) -> Option<usize> {
+        let index = match target.parse::<usize>() {
+            Ok(index) => index,
+            Err(_) => self
+                .group_names
+                .iter()
+                .position(|n| n.as_deref() == Some(target))?,
+        };
+        Some(index).filter(|&index| index < self.group_names.len())
+    }
+
+    // Counts a group or class opening at `start` towards the nesting limit.
+    fn enter(&mut self, start: usize) -> Result<(), Error> {
+        if self.depth >= self.nesting_limit {
+            let kind = ErrorKind::NestingTooDeep(self.nesting_limit);
+            return Err(self.error_from(start, kind));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn inline_flags(&mut self) -> Result<Flags, Error> {
+        let mut flags = self.flags;
+        let mut enabled = true;
+
+        while self.has_more_chars() && self.peek() != ')' && self.peek() != ':' {
+            let c = self.next()?;
+            if c == '-' && enabled {
+                enabled = false;
+            } else if !flags.set(c, enabled) {
+                return Err(self.error_from(self.position - 1, ErrorKind::UnknownGroupFlag(c)));
+            }
+        }
+
+        Ok(flags)
+    }
+
+    fn group_name(&mut self) -> Result<String, Error> {
+        self.match_('<')?;
+        self.name_until('>')
+    }
+
+    // Scans a group name up to and including `end`.
+    fn name_until(&mut self, end: char) -> Result<String, Error> {
+        let start = self.position;
+        let mut name = String::new();
+        while self.has_more_chars() && self.peek() != end {
+            name.push(self.next()?);
+        }
+        if !self.has_more_chars() {
+            return Err(self.error_from(start, ErrorKind::UnterminatedGroupName));
+        }
+        let name_end = self.position;
+        self.match_(end)?;
+
+        let mut chars = name.chars();
+        let valid = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            let kind = ErrorKind::InvalidGroupName(name);
+            return Err(Error::new(kind, &self.pattern, start..name_end));
+        }
+
+        Ok(name)
+    }
+
+    fn add_group(&mut self, name: Option<String>) -> Result<usize, ErrorKind> {
+        if let Some(name) = &name {
+            if self.group_names.iter().flatten().any(|n| n == name) {
+                return Err(ErrorKind::DuplicateGroupName(name.clone()));
+            }
+        }
+
+        self.group_names.push(name);
+        Ok(self.group_names.len() - 1)
+    }
+
+    // Nested classes are kept on an explicit stack, like groups, so that
+    // parsing them takes no stack frame per level.
+    fn class_set(&mut self) -> Result<CharClass, Error> {
+        let mut stack = vec![self.open_class()?];
+        loop {
+            let frame = stack.last_mut().unwrap();
+            if self.class_union(frame)? {
+                let nested = self.open_class()?;
+                stack.push(nested);
+                continue;
+            }
+
+            if frame.items == 0 {
+                return Err(self.error_from(frame.start, ErrorKind::EmptyClass));
+            }
+            frame.items = 0;
+            let union = std::mem::replace(&mut frame.union, CharClass::new());
+            let mut class = match frame.left.take() {
+                Some((left, '&')) => left.intersect(&union),
+                Some((left, '-')) => left.difference(&union),
+                Some((left, _)) => left.symmetric_difference(&union),
+                None => union,
+            };
+            if let Some(op) = self.class_op()? {
+                frame.left = Some((class, op));
+                continue;
+            }
+
+            self.match_(']')?;
+            self.depth -= 1;
+            if self.flags.case_insensitive {
+                class = class.case_fold();
+            }
+            if stack.pop().unwrap().negated {
+                class = class.negate();
+            }
+            match stack.last_mut() {
+                Some(outer) => outer.union = outer.union.union(&class),
+                None => return Ok(class),
+            }
+        }
+    }
+
+    fn open_class(&mut self) -> Result<ClassFrame, Error> {
+        let start = self.position;
+        self.enter(start)?;
+        self.match_('[')?;
+
+        let negated = self.has_more_chars() && self.peek() == '^';
+        if negated {
+            self.match_('^')?;
+        }
+
+        Ok(ClassFrame {
+            start,
+            negated,
+            left: None,
+            union: CharClass::new(),
+            items: 0,
+        })
+    }
+
+    fn is_class_op(&self) -> bool {
+        match (self.peek_nth(0), self.peek_nth(1)) {
+            (Some(a @ ('&' | '-' | '~')), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    fn class_op(&mut self) -> Result<Option<char>, Error> {
+        if !self.is_class_op() {
+            return Ok(None);
+        }
+
+        let op = self.next()?;
+        self.next()?;
+        Ok(Some(op))
+    }
+
+    // Reads the items of `frame` up to its closing bracket or next set
+    // operator, or up to a nested class, then giving true for the caller to
+    // parse it.
+    fn class_union(&mut self, frame: &mut ClassFrame) -> Result<bool, Error> {
+        let class = &mut frame.union;
+        loop {
+            if !self.has_more_chars() {
+                return Err(self.error_from(frame.start, ErrorKind::UnterminatedClass));
+            }
+            if self.peek() == ']' || self.is_class_op() {
+                return Ok(false);
+            }
+
+            frame.items += 1;
+            if self.peek() == '[' {
+                if self.peek_nth(1) != Some(':') {
+                    return Ok(true);
+                }
+                *class = class.union(&self.posix_class()?);
+                continue;
+            }
+            if let Some(shorthand) = self.shorthand()? {
+                *class = class.union(&shorthand);
+                continue;
+            }
+
+            let range_start = self.position;
+            let first = self.class_char()?;
+            // A '-' right before the closing bracket or a set operator is a literal.
+            if !self.has_more_chars()
+                || self.peek() != '-'
+                || self.peek_nth(1).is_none_or(|c| c == ']' || c == '-')
+            {
+                class.push(first, first);
+                continue;
+            }
+
+            // Invalid ranges are reported, then read as the author most
+            // likely meant them.
+            self.match_('-')?;
+            if let Some(shorthand) = self.shorthand()? {
+                let error = self
+                    .error_from(range_start, ErrorKind::InvalidClassRange)
+                    .with_help(escape_help('-'));
+                self.errors.push(error);
+                class.push(first, first);
+                class.push('-', '-');
+                *class = class.union(&shorthand);
+                continue;
+            }
+            let last = self.class_char()?;
+            if last < first {
+                let error = self
+                    .error_from(range_start, ErrorKind::InvalidClassRange)
+                    .with_help(format!("did you mean `{}-{}`?", last, first));
+                self.errors.push(error);
+            }
+            class.push(first.min(last), first.max(last));
+        }
+    }
+
+    fn posix_class(&mut self) -> Result<CharClass, Error> {
+        let start = self.position;
+        self.match_('[')?;
+        self.match_(':')?;
+
+        let negated = self.has_more_chars() && self.peek() == '^';
+        if negated {
+            self.match_('^')?;
+        }
+
+        let mut name = String::new();
+        while self.has_more_chars() && self.peek() != ':' {
+            name.push(self.next()?);
+        }
+        if self.peek_nth(0) != Some(':') || self.peek_nth(1) != Some(']') {
+            return Err(self.error_from(start, ErrorKind::UnterminatedPosixClass));
+        }
+        self.match_(':')?;
+        self.match_(']')?;
+
+        let class = CharClass::posix(&name)
+            .ok_or_else(|| self.error_from(start, ErrorKind::UnknownPosixClass(name)))?;
+        Ok(if negated { class.negate() } else { class })
+    }
+
+    fn assertion(&mut self) -> Result<Option<Assertion>, Error> {
+        let assertion = match (self.peek(), self.peek_nth(1)) {
+            ('^', _) => self.flags.caret(),
+            ('$', _) => self.flags.dollar(),
+            ('\\', Some('A')) => Assertion::StartText,
+            ('\\', Some('z')) => Assertion::EndText,
+            ('\\', Some('b')) => self.flags.word_boundary(false),
+            ('\\', Some('B')) => self.flags.word_boundary(true),
+            _ => return Ok(None),
+        };
+
+        if self.next()? == '\\' {
+            self.next()?;
+        }
+        Ok(Some(assertion))
+    }
+
+    fn backref(&mut self) -> Result<Option<usize>, Error> {
+        if self.peek() != '\\' {
+            return Ok(None);
+        }
+
+        let start = self.position;
+        let index = match self.peek_nth(1) {
+            Some('1'..='9') => {
+                self.match_('\\')?;
+                let mut digits = String::new();
+                while self.has_more_chars() && self.peek().is_ascii_digit() {
+                    digits.push(self.next()?);
+                }
+                digits.parse::<usize>().map_err(|_| {
+                    let kind = ErrorKind::UndefinedBackreference(format!("\\{}", digits));
+                    self.error_from(start, kind)
+                })?
+            }
+            Some('k') if self.peek_nth(2) == Some('<') => {
+                self.match_('\\')?;
+                self.match_('k')?;
+                let name = self.group_name()?;
+                self.group_names
+                    .iter()
+                    .position(|n| n.as_ref() == Some(&name))
+                    .ok_or_else(|| {
+                        self.error_from(start, ErrorKind::UndefinedBackreference(name))
+                    })?
+            }
+            _ => return Ok(None),
+        };
+
+        self.backrefs.push((index, start..self.position));
+        self.backtracking
+            .get_or_insert(("backreferences", start..self.position));
+        Ok(Some(index))
+    }
+
+    fn shorthand(&mut self) -> Result<Option<CharClass>, Error> {
+        if self.peek() != '\\' {
+            return Ok(None);
+        }
+
+        if let Some(p @ ('p' | 'P')) = self.peek_nth(1) {
             self.match_('\\')?;
-            vec![
-                TreeNode::from_label('\\'),
-                TreeNode::from_label(self.next()?),
-            ]
+            self.match_(p)?;
+            let mut class = self.property()?;
+            if self.flags.case_insensitive {
+                class = class.case_fold();
+            }
+            return Ok(Some(if p == 'P' { class.negate() } else { class }));
+        }
+
+        match self.peek_nth(1).and_then(|c| self.flags.shorthand_class(c)) {
+            Some(class) => {
+                self.match_('\\')?;
+                self.next()?;
+                Ok(Some(class))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Parses the name of a property, after its '\p' or '\P'.
+    fn property(&mut self) -> Result<CharClass, Error> {
+        let start = self.position - 2;
+        let name = if self.has_more_chars() && self.peek() == '{' {
+            self.match_('{')?;
+            let mut name = String::new();
+            while self.has_more_chars() && self.peek() != '}' {
+                name.push(self.next()?);
+            }
+            if !self.has_more_chars() {
+                return Err(self.error_from(start, ErrorKind::UnterminatedProperty));
+            }
+            self.match_('}')?;
+            name
         } else {
-            vec![TreeNode::from_label(self.next()?)]
+            self.next()?.to_string()
         };
 
-        Ok(TreeNode::new("Char".to_string(), children))
+        CharClass::property(&name)
+            .ok_or_else(|| self.error_from(start, ErrorKind::UnknownProperty(name)))
+    }
+
+    fn class_char(&mut self) -> Result<char, Error> {
+        if self.peek() == '\\' {
+            self.escape(true)
+        } else {
+            self.next()
+        }
+    }
+
+    fn char_(&mut self) -> Result<Ast, Error> {
+        let start = self.position;
+        // A quantifier with nothing to repeat is reported, then read as the
+        // literal it was probably meant to be.
+        let c = if is_meta_char(self.peek()) || self.repeat().is_some() {
+            let len = self.repeat().map_or(1, |repeat| repeat.len);
+            let c = self.peek();
+            let span = self.position..self.position + len;
+            let error = Error::new(ErrorKind::MissingOperand, &self.pattern, span)
+                .with_help(escape_help(c));
+            self.errors.push(error);
+            self.position += 1;
+            c
+        } else if self.peek() == '\\' {
+            self.escape(false)?
+        } else {
+            self.next()?
+        };
+
+        let kind = if self.flags.case_insensitive {
+            AstKind::Class(CharClass::from_char(c).case_fold())
+        } else {
+            AstKind::Literal(c)
+        };
+        Ok(Ast::new(kind, start..self.position))
+    }
+
+    // A malformed escape is reported, then read as the char after the
+    // backslash, or as a backslash if there is none. `in_class` tells whether
+    // the escape is in a class.
+    fn escape(&mut self, in_class: bool) -> Result<char, Error> {
+        let start = self.position;
+        self.match_('\\')?;
+        match parse_escape(self.rest()) {
+            Ok((c, len)) => {
+                self.position += len;
+                Ok(c)
+            }
+            Err(kind) => {
+                let kind = match kind {
+                    ErrorKind::BackreferenceUnsupported(c) if in_class => {
+                        ErrorKind::BackreferenceInClass(c)
+                    }
+                    kind => kind,
+                };
+                let help = match kind {
+                    ErrorKind::TrailingBackslash => Some(escape_help('\\')),
+                    ErrorKind::UnknownEscape(c) => {
+                        Some(format!("remove the backslash to match `{}`", c))
+                    }
+                    _ => None,
+                };
+                let mut error = Error::new(kind, &self.pattern, start..self.position + 1);
+                if let Some(help) = help {
+                    error = error.with_help(help);
+                }
+                self.errors.push(error);
+                if self.has_more_chars() {
+                    self.next()
+                } else {
+                    Ok('\\')
+                }
+            }
+        }
     }
 
     // --------------
 
-    fn next(&mut self) -> Result<char, String> {
+    // Skips `(?#...)` comments and, in verbose mode, whitespace and `#` comments
+    // running to the end of the line.
+    fn skip_trivia(&mut self) -> Result<(), Error> {
+        while self.syntax != Syntax::Basic && self.has_more_chars() {
+            let c = self.peek();
+            if c == '(' && self.peek_nth(1) == Some('?') && self.peek_nth(2) == Some('#') {
+                let start = self.position;
+                while self.has_more_chars() && self.peek() != ')' {
+                    self.next()?;
+                }
+                if !self.has_more_chars() {
+                    return Err(self.error_from(start, ErrorKind::UnterminatedComment));
+                }
+                self.match_(')')?;
+            } else if self.flags.verbose && c.is_whitespace() {
+                self.next()?;
+            } else if self.flags.verbose && c == '#' {
+                while self.has_more_chars() && self.next()? != '\n' {}
+            } else {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Matches the ')' closing the group that starts at `start`. A group still
+    // open at the end of the pattern is reported, and closed there.
+    fn close_group(&mut self, start: usize) -> Result<(), Error> {
+        if self.has_more_chars() {
+            return self.match_(')');
+        }
+
+        let error = Error::new(
+            ErrorKind::UnbalancedParenthesis,
+            &self.pattern,
+            start..start + 1,
+        )
+        .with_help("close the group with `)`, or write `\\(` to match `(`");
+        self.errors.push(error);
+        Ok(())
+    }
+
+    fn repeat(&self) -> Option<Repeat> {
+        if self.syntax == Syntax::Basic {
+            return None;
+        }
+        parse_repeat(self.rest())
+    }
+
+    fn next(&mut self) -> Result<char, Error> {
+        if !self.has_more_chars() {
+            return Err(self.error(ErrorKind::UnexpectedEnd));
+        }
         let c = self.peek();
         self.match_(c)?;
         Ok(c)
     }
 
-    fn match_(&mut self, symbol: char) -> Result<(), String> {
-        if self.peek() != symbol {
-            Err(format!("unexpected symbol: {}", symbol))
+    fn match_(&mut self, symbol: char) -> Result<(), Error> {
+        if !self.has_more_chars() || self.peek() != symbol {
+            Err(self.error(ErrorKind::Expected(symbol)))
         } else {
             self.position += 1;
             Ok(())
         }
     }
 
+    // An error on the char at the current position.
+    fn error(&self, kind: ErrorKind) -> Error {
+        self.error_from(self.position, kind)
+    }
+
+    // An error from `start` to the current position, or on the char at `start`
+    // if nothing was consumed since.
+    fn error_from(&self, start: usize, kind: ErrorKind) -> Error {
+        let end = self.position.max(start + 1);
+        Error::new(kind, &self.pattern, start..end)
+    }
+
     fn has_more_chars(&self) -> bool {
-        self.position < self.pattern.len()
+        self.position < self.chars.len()
     }
 
     fn peek(&self) -> char {
-        self.pattern.chars().nth(self.position).unwrap()
+        self.chars[self.position]
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.chars.get(self.position + n).copied()
+    }
+
+    // The chars from the current position on.
+    fn rest(&self) -> impl Iterator<Item = char> + '_ {
+        self.chars[self.position..].iter().copied()
     }
 }
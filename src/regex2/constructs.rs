@@ -1,9 +1,13 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
-use std::fmt::{Display, Error, Formatter};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
 use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 
+use crate::ast::{Ast, AstKind, Group, GroupKind, Repetition};
+use crate::error::Error;
+use crate::states::{drop_successors, Successors};
+
 type RNfaState = Rc<RefCell<NfaState>>;
 
 pub struct NfaState {
@@ -30,6 +34,20 @@ impl NfaState {
     }
 }
 
+impl Successors for NfaState {
+    fn take_successors(&mut self) -> Vec<RNfaState> {
+        let mut successors: Vec<RNfaState> = self.epsilon.drain(..).collect();
+        successors.extend(self.delta.drain().map(|(_, to)| to));
+        successors
+    }
+}
+
+impl Drop for NfaState {
+    fn drop(&mut self) {
+        drop_successors(self);
+    }
+}
+
 impl Hash for NfaState {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.name.hash(state);
@@ -45,7 +63,121 @@ impl PartialEq for NfaState {
 impl Eq for NfaState {}
 
 impl Display for NfaState {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "NfaState[name={}]", self.name)
     }
 }
+
+// ----------
+
+pub struct Nfa {
+    start: RNfaState,
+    end: RNfaState,
+}
+
+impl Nfa {
+    /// Builds the NFA of `ast`, parsed from `pattern` in the `Basic` syntax,
+    /// with Thompson's construction.
+    pub fn from_ast(pattern: &str, ast: &Ast) -> Result<Nfa, Error> {
+        let mut count = 0;
+        let mut state = || {
+            count += 1;
+            Rc::new(RefCell::new(NfaState::new(count)))
+        };
+        ast.fold(
+            |_| 1,
+            |ast, operands| build(pattern, ast, operands, &mut state),
+        )
+    }
+
+    /// Whether the NFA matches the whole of `haystack`.
+    pub fn is_full_match(&self, haystack: &str) -> bool {
+        let mut current = closure(vec![self.start.clone()]);
+        for c in haystack.chars() {
+            let targets = current
+                .iter()
+                .filter_map(|state| state.borrow().delta.get(&c).cloned())
+                .collect();
+            current = closure(targets);
+        }
+
+        current.iter().any(|state| Rc::ptr_eq(state, &self.end))
+    }
+}
+
+// Builds the NFA of `ast` from `operands`, those of its children, see
+// `Ast::fold`.
+fn build<F>(pattern: &str, ast: &Ast, operands: Vec<Nfa>, state: &mut F) -> Result<Nfa, Error>
+where
+    F: FnMut() -> RNfaState,
+{
+    let mut operands = operands.into_iter();
+    let nfa = match &ast.kind {
+        AstKind::Literal(c) => {
+            let (start, end) = (state(), state());
+            start.borrow_mut().add_delta(*c, end.clone());
+            Nfa { start, end }
+        }
+        AstKind::Empty => {
+            let (start, end) = (state(), state());
+            start.borrow_mut().add_epsilon(end.clone());
+            Nfa { start, end }
+        }
+        AstKind::Concat(_) => {
+            let mut nfa = operands.next().unwrap();
+            for n2 in operands {
+                nfa.end.borrow_mut().add_epsilon(n2.start);
+                nfa.end = n2.end;
+            }
+            nfa
+        }
+        AstKind::Alternation(_) => {
+            let (start, end) = (state(), state());
+            for n1 in operands {
+                start.borrow_mut().add_epsilon(n1.start);
+                n1.end.borrow_mut().add_epsilon(end.clone());
+            }
+            Nfa { start, end }
+        }
+        AstKind::Repetition(Repetition {
+            min: min @ (0 | 1),
+            max,
+            ..
+        }) if max.is_none() || (*min, *max) == (0, Some(1)) => {
+            let n1 = operands.next().unwrap();
+            let (start, end) = (state(), state());
+            start.borrow_mut().add_epsilon(n1.start.clone());
+            if *min == 0 {
+                start.borrow_mut().add_epsilon(end.clone());
+            }
+            if max.is_none() {
+                n1.end.borrow_mut().add_epsilon(n1.start);
+            }
+            n1.end.borrow_mut().add_epsilon(end.clone());
+            Nfa { start, end }
+        }
+        AstKind::Group(Group {
+            kind: GroupKind::Capture(_) | GroupKind::NonCapture,
+            ..
+        }) => operands.next().unwrap(),
+        _ => return Err(ast.unsupported(pattern, "not supported by regex2")),
+    };
+    Ok(nfa)
+}
+
+// The states reachable from `starts` by epsilon transitions, `starts`
+// included, found as by the `closure` of `regex0`.
+fn closure(starts: Vec<RNfaState>) -> Vec<RNfaState> {
+    let mut states = vec![];
+    let mut seen: HashSet<usize> = HashSet::new();
+    let mut stack = starts;
+    stack.reverse();
+    while let Some(state) = stack.pop() {
+        if !seen.insert(state.borrow().name) {
+            continue;
+        }
+        stack.extend(state.borrow().epsilon.iter().rev().cloned());
+        states.push(state);
+    }
+    states
+}
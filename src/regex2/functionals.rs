@@ -1,4 +0,0 @@
-fn shunting_yard(tokens: &str) {
-    for token in tokens.chars() {
-    }
-}
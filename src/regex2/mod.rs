@@ -1,2 +1,32 @@
-mod constructs;
-mod functionals;
+use crate::error::{Error, ErrorKind};
+use crate::options::Options;
+use crate::regex1::parser::Parser;
+use crate::regex2::constructs::Nfa;
+use crate::syntax::Syntax;
+
+pub mod constructs;
+
+/// Compiles `pattern`, made of literal characters, escapes, groups, `|` and
+/// the `*`, `+` and `?` quantifiers, see `Syntax::Basic`.
+pub fn compile(pattern: &str) -> Result<Nfa, Error> {
+    compile_with_options(pattern, Options::default())
+}
+
+/// Like `compile`, with `options`. Of the flags, only those without effect on
+/// the `Basic` syntax may be set: it has no classes for case-insensitive
+/// letters to become, nor comments for verbose mode to skip.
+pub fn compile_with_options(pattern: &str, options: Options) -> Result<Nfa, Error> {
+    let flags = [
+        ('i', options.flags.case_insensitive),
+        ('x', options.flags.verbose),
+    ];
+    if let Some((flag, _)) = flags.iter().find(|(_, set)| *set) {
+        let reason = format!("the `{}` flag is not supported by regex2", flag);
+        let span = 0..pattern.chars().count();
+        return Err(Error::new(ErrorKind::Unsupported(reason), pattern, span));
+    }
+
+    let mut parser = Parser::with_options(pattern.to_string(), options);
+    parser.set_syntax(Syntax::Basic);
+    Nfa::from_ast(pattern, &parser.parse()?)
+}
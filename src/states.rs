@@ -0,0 +1,24 @@
+//! Dropping the states of the automata, each holding the states it has
+//! transitions to. A chain of them can be as long as the pattern, and dropping
+//! it recursively would take one stack frame per state, so the `Drop` of every
+//! kind of state calls `drop_successors` instead.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A state holding the states it has transitions to.
+pub(crate) trait Successors: Sized {
+    /// Takes the states this one has transitions to out of it.
+    fn take_successors(&mut self) -> Vec<Rc<RefCell<Self>>>;
+}
+
+/// Drops the successors of `state`, and theirs in turn, in a loop. Those other
+/// states still hold are left to them.
+pub(crate) fn drop_successors<S: Successors>(state: &mut S) {
+    let mut pending = state.take_successors();
+    while let Some(state) = pending.pop() {
+        if let Ok(state) = Rc::try_unwrap(state) {
+            pending.extend(state.into_inner().take_successors());
+        }
+    }
+}
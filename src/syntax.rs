@@ -0,0 +1,204 @@
+//! Lexical helpers shared by the pattern front ends.
+
+use std::iter::Peekable;
+
+use crate::error::ErrorKind;
+
+/// Largest bound accepted in a counted repetition. Repetitions are expanded
+/// into copies of their operand, so unbounded counts would blow up the automata.
+pub const MAX_REPEAT: usize = 1000;
+
+/// Largest number of atoms a repetition may expand to, counting the copies
+/// made for every enclosing counted repetition, so that nesting them cannot
+/// blow up the automata either.
+pub const MAX_EXPANSION: usize = 100_000;
+
+/// Default for how deeply groups, and classes where a front end nests them, may
+/// nest. Deeper patterns are rejected with `ErrorKind::NestingTooDeep` rather
+/// than risk overflowing the stack of whatever walks their structure.
+pub const NESTING_LIMIT: usize = 250;
+
+/// Most the nesting limit may be raised to, higher limits being lowered to it.
+/// Trees are built and dropped without recursion, but lookarounds and atomic
+/// groups are still matched recursively.
+pub const MAX_NESTING_LIMIT: usize = 500;
+
+/// The syntax a front end reads its patterns in, all with the same parser.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Syntax {
+    /// Literal characters, escapes, groups, `|` and the `*`, `+` and `?`
+    /// quantifiers, which may be stacked, as `regex2` reads them. Any other
+    /// character is a literal.
+    Basic,
+    /// The syntax of `regex1`, see the grammar in `regex1::parser`.
+    #[default]
+    Extended,
+    /// `Extended` with the `&` intersection and `~` complement operators of
+    /// `regex0`.
+    Boolean,
+}
+
+/// A counted repetition `{n}`, `{n,}` or `{n,m}`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Repeat {
+    pub min: usize,
+    pub max: Option<usize>,
+    /// Number of chars the repetition spans in the pattern, braces included.
+    pub len: usize,
+}
+
+impl Repeat {
+    pub fn validate(&self) -> Result<(), ErrorKind> {
+        for bound in std::iter::once(self.min).chain(self.max) {
+            if bound > MAX_REPEAT {
+                return Err(ErrorKind::RepeatTooLarge(bound));
+            }
+        }
+
+        match self.max {
+            Some(max) if max < self.min => Err(ErrorKind::InvalidRepeatRange(self.min, max)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Scans a counted repetition at the start of `chars`. Returns `None` if the
+/// input does not have the shape of one, in which case `{` is an ordinary
+/// character. The bounds are not checked, see `Repeat::validate`.
+pub fn parse_repeat<I: Iterator<Item = char>>(chars: I) -> Option<Repeat> {
+    let mut chars = chars.peekable();
+    if chars.next() != Some('{') {
+        return None;
+    }
+
+    let (min, min_len) = scan_number(&mut chars)?;
+    let (max, len) = match chars.next()? {
+        '}' => (Some(min), min_len + 2),
+        ',' if chars.peek() == Some(&'}') => (None, min_len + 3),
+        ',' => {
+            let (max, max_len) = scan_number(&mut chars)?;
+            if chars.next()? != '}' {
+                return None;
+            }
+            (Some(max), min_len + max_len + 3)
+        }
+        _ => return None,
+    };
+
+    Some(Repeat { min, max, len })
+}
+
+/// Scans a decimal number, saturating on overflow. Returns the value and the
+/// number of digits.
+fn scan_number<I: Iterator<Item = char>>(chars: &mut Peekable<I>) -> Option<(usize, usize)> {
+    let mut value = 0usize;
+    let mut len = 0;
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        value = value.saturating_mul(10).saturating_add(digit as usize);
+        len += 1;
+        chars.next();
+    }
+
+    if len == 0 {
+        None
+    } else {
+        Some((value, len))
+    }
+}
+
+/// Scans the escape sequence following a `\` that stands for a single
+/// character: a control escape (`\n`, `\t`, `\r`, `\f`, `\v`, `\a`, `\e`), a
+/// code point in hex (`\xHH`, `\x{H..}`, `\uHHHH`, `\u{H..}`) or octal (`\0`,
+/// `\0o`, `\0oo`, `\o{O..}`), or an escaped character that is not an ASCII
+/// letter or digit. Returns the character and the number of chars scanned.
+///
+/// Front ends resolve their own letter escapes, such as shorthand classes and
+/// assertions, before falling back to this.
+pub fn parse_escape<I: Iterator<Item = char>>(chars: I) -> Result<(char, usize), ErrorKind> {
+    let mut chars = chars.peekable();
+    let c = match chars.next() {
+        Some(c) => c,
+        None => return Err(ErrorKind::TrailingBackslash),
+    };
+
+    let control = match c {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        'f' => Some('\x0C'),
+        'v' => Some('\x0B'),
+        'a' => Some('\x07'),
+        'e' => Some('\x1B'),
+        _ => None,
+    };
+    if let Some(control) = control {
+        return Ok((control, 1));
+    }
+
+    let (radix, digits, len) = match c {
+        'x' => scan_code(&mut chars, 16, Some(2))?,
+        'u' => scan_code(&mut chars, 16, Some(4))?,
+        'o' => scan_code(&mut chars, 8, None)?,
+        // At most two more octal digits, so `\08` is NUL followed by '8'.
+        '0' => {
+            let mut digits = String::from("0");
+            while digits.len() < 3 && chars.peek().is_some_and(|c| c.is_digit(8)) {
+                digits.push(chars.next().unwrap());
+            }
+            let len = digits.len() - 1;
+            (8, digits, len)
+        }
+        '1'..='9' | 'k' => return Err(ErrorKind::BackreferenceUnsupported(c)),
+        _ if c.is_ascii_alphanumeric() => return Err(ErrorKind::UnknownEscape(c)),
+        _ => return Ok((c, 1)),
+    };
+
+    let code = u32::from_str_radix(&digits, radix)
+        .map_err(|_| ErrorKind::InvalidEscape(format!("\\{}{}", c, digits)))?;
+    match std::char::from_u32(code) {
+        Some(value) => Ok((value, len + 1)),
+        None => Err(ErrorKind::InvalidCodePoint(code)),
+    }
+}
+
+/// Scans the digits of a code point escape, either braced or exactly `fixed`
+/// digits long. Returns the radix, the digits and the number of chars scanned.
+fn scan_code<I: Iterator<Item = char>>(
+    chars: &mut Peekable<I>,
+    radix: u32,
+    fixed: Option<usize>,
+) -> Result<(u32, String, usize), ErrorKind> {
+    let mut digits = String::new();
+    if chars.peek() == Some(&'{') {
+        chars.next();
+        for c in chars.by_ref() {
+            if c == '}' {
+                if digits.is_empty() || digits.len() > 8 {
+                    break;
+                }
+                let len = digits.len() + 2;
+                return Ok((radix, digits, len));
+            }
+            digits.push(c);
+        }
+        return Err(ErrorKind::InvalidEscape(format!("{{{}", digits)));
+    }
+
+    let fixed = match fixed {
+        Some(fixed) => fixed,
+        None => return Err(ErrorKind::InvalidEscape("expected '{'".to_string())),
+    };
+    while digits.len() < fixed {
+        match chars.next() {
+            Some(c) if c.is_digit(radix) => digits.push(c),
+            _ => {
+                return Err(ErrorKind::InvalidEscape(format!(
+                    "expected {} digits",
+                    fixed
+                )))
+            }
+        }
+    }
+
+    Ok((radix, digits, fixed))
+}
@@ -0,0 +1,96 @@
+//! Anchors: `^` and `$` at the ends of the text or, in multi-line mode, of its
+//! lines, and `\A` and `\z` at the ends of the text only.
+
+mod common;
+
+use regex::flags::Flags;
+use regex::regex1::nfa::{find, infix_to_nfa_with_flags};
+
+#[test]
+fn text_anchors() {
+    common::check_find(&[
+        ("^a", "ab", Some((0, 1))),
+        ("^a", "ba", None),
+        ("a$", "ba", Some((1, 2))),
+        ("a$", "ab", None),
+        ("^$", "", Some((0, 0))),
+        ("a^b", "ab", None),
+        ("\\Aa", "aa", Some((0, 1))),
+        ("a\\z", "aa", Some((1, 2))),
+        ("^b", "a\nb", None),
+        ("a$", "a\nb", None),
+    ]);
+}
+
+#[test]
+fn multi_line_mode() {
+    let mut flags = Flags::new();
+    flags.multi_line = true;
+    let find =
+        |pattern, haystack| find(&infix_to_nfa_with_flags(pattern, flags).unwrap(), haystack);
+    assert_eq!(find("^b", "a\nb"), Some((2, 3)));
+    assert_eq!(find("a$", "a\nb"), Some((0, 1)));
+    assert_eq!(find("^$", "a\n\nb"), Some((2, 2)));
+    assert_eq!(find("\\Ab", "a\nb"), None);
+    assert_eq!(find("a\\z", "a\nb"), None);
+}
+
+#[test]
+fn inline_multi_line() {
+    common::check_find(&[
+        ("(?m)^b$", "a\nb\nc", Some((2, 3))),
+        ("(?m:^b)$", "a\nb\nc", None),
+        ("(?m:^b)", "a\nb", Some((2, 3))),
+        ("(?m)a(?-m:$)", "a\nb", None),
+    ]);
+}
+
+#[test]
+fn every_front_end() {
+    common::check_extended(
+        &["", "a", "ab"],
+        &[
+            ("^a$", [false, true, false]),
+            ("^a*$", [true, true, false]),
+            ("a^", [false, false, false]),
+            ("$a", [false, false, false]),
+            ("^(a|$)b?$", [true, true, true]),
+        ],
+    );
+}
+
+// Each pattern and haystack, with whether the pattern matches all of it.
+// `regex0` checks assertions on the states they sit on, so optional ones must
+// not make their state mandatory.
+const OPTIONAL: [(&str, &str, bool); 14] = [
+    ("^a$", "a", true),
+    ("a^", "a", false),
+    ("a^?", "a", true),
+    ("a^*", "a", true),
+    ("$a", "a", false),
+    ("$?a?", "a", true),
+    ("$?a?", "", true),
+    ("\\b", "", false),
+    ("\\b?", "", true),
+    ("a\\b?b", "ab", true),
+    ("a\\bb", "ab", false),
+    ("a\\B?b", "ab", true),
+    ("(\\b|x)?a", "a", true),
+    ("(a\\b)?b", "b", true),
+];
+
+#[test]
+fn optional_assertions() {
+    for (engine, is_match) in common::EXTENDED {
+        for (pattern, haystack, expected) in OPTIONAL {
+            assert_eq!(
+                is_match(pattern, haystack),
+                expected,
+                "{} on pattern {:?} and haystack {:?}",
+                engine,
+                pattern,
+                haystack
+            );
+        }
+    }
+}
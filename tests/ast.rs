@@ -0,0 +1,115 @@
+//! Every front end parses into the same tree, and engines report what they
+//! cannot match at the node it comes from.
+
+use regex::ast::{Ast, AstKind, Greed, Group, GroupKind, Repetition};
+use regex::error::ErrorKind;
+use regex::flags::Flags;
+use regex::regex1::parser::Parser;
+use regex::syntax::Syntax;
+use regex::{pda, regex0, regex1, regex2};
+
+fn parse(pattern: &str, syntax: Syntax) -> Ast {
+    let mut parser = Parser::new(pattern.to_string());
+    parser.set_syntax(syntax);
+    parser.parse().unwrap()
+}
+
+fn literal(c: char, at: usize) -> Ast {
+    Ast::new(AstKind::Literal(c), at..at + 1)
+}
+
+fn repetition(ast: Ast, max: Option<usize>, greed: Greed, end: usize) -> Ast {
+    let span = ast.span.start..end;
+    let repetition = Repetition {
+        min: 0,
+        max,
+        greed,
+        ast: Box::new(ast),
+    };
+    Ast::new(AstKind::Repetition(repetition), span)
+}
+
+#[test]
+fn extended() {
+    let concat = Ast::new(
+        AstKind::Concat(vec![literal('a', 0), literal('b', 1)]),
+        0..2,
+    );
+    let lazy = repetition(literal('c', 3), None, Greed::Lazy, 6);
+    let expected = Ast::new(AstKind::Alternation(vec![concat, lazy]), 0..6);
+    assert_eq!(parse("ab|c*?", Syntax::Extended), expected);
+
+    let group = Group {
+        kind: GroupKind::Capture(1),
+        ast: Box::new(Ast::new(AstKind::Empty, 1..1)),
+    };
+    let expected = Ast::new(AstKind::Group(group), 0..2);
+    assert_eq!(parse("()", Syntax::Extended), expected);
+
+    let ast = parse("(?i)a", Syntax::Extended);
+    assert_eq!(ast.children()[0].kind, AstKind::Empty);
+    assert!(matches!(ast.children()[1].kind, AstKind::Class(_)));
+}
+
+#[test]
+fn boolean() {
+    let complement = Ast::new(AstKind::Complement(Box::new(literal('a', 1))), 0..2);
+    let expected = Ast::new(
+        AstKind::Intersection(vec![complement, literal('b', 3)]),
+        0..4,
+    );
+    assert_eq!(parse("~a&b", Syntax::Boolean), expected);
+
+    let error = regex0::regex::compile("a|~".to_string()).err().unwrap();
+    assert_eq!(error.kind(), &ErrorKind::MissingOperand);
+    assert_eq!(error.char_span(), 3..3);
+}
+
+#[test]
+fn basic() {
+    let inner = repetition(literal('a', 0), Some(1), Greed::Greedy, 2);
+    let expected = repetition(inner, None, Greed::Greedy, 3);
+    assert_eq!(parse("a?*", Syntax::Basic), expected);
+
+    let expected = Ast::new(
+        AstKind::Concat(vec![literal('[', 0), literal('.', 1), literal('{', 2)]),
+        0..3,
+    );
+    assert_eq!(parse("[.{", Syntax::Basic), expected);
+}
+
+#[test]
+fn unsupported_spans() {
+    let error = regex0::regex::compile("x(^a&b)".to_string()).err().unwrap();
+    assert!(matches!(error.kind(), ErrorKind::Unsupported(_)));
+    assert_eq!(error.char_span(), 2..6);
+
+    let error = pda::compile("x(?=a)").err().unwrap();
+    assert!(matches!(error.kind(), ErrorKind::Unsupported(_)));
+    assert_eq!(error.char_span(), 1..6);
+
+    let error = regex1::create_matcher("a(b)\\1").err().unwrap();
+    assert!(matches!(error.kind(), ErrorKind::Unsupported(_)));
+    assert_eq!(error.char_span(), 4..6);
+}
+
+#[test]
+fn unsupported_flags() {
+    for (flag, reason) in [
+        ('i', "the `i` flag is not supported by regex2"),
+        ('x', "the `x` flag is not supported by regex2"),
+    ] {
+        let mut flags = Flags::new();
+        flags.set(flag, true);
+        let error = regex2::compile_with_options("ab", flags.into())
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), &ErrorKind::Unsupported(reason.to_string()));
+        assert_eq!(error.char_span(), 0..2);
+    }
+
+    let mut flags = Flags::new();
+    flags.set('s', true);
+    let nfa = regex2::compile_with_options("a.", flags.into()).unwrap();
+    assert!(nfa.is_full_match("a."));
+}
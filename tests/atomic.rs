@@ -0,0 +1,83 @@
+//! Atomic groups and possessive quantifiers, which only the backtracking
+//! engine matches.
+
+use regex::error::ErrorKind;
+use regex::regex1::backtrack;
+use regex::regex1::create_matcher;
+use regex::regex1::nfa::infix_to_nfa;
+use regex::{pda, regex0};
+
+// The byte span of a match, if any.
+type Match = Option<(usize, usize)>;
+
+// Each pattern and haystack, with the match found.
+const CASES: [(&str, &str, Match); 16] = [
+    // Once the group has matched, its choices are never revisited.
+    ("(?>a+)b", "aaab", Some((0, 4))),
+    ("(?>a+)a", "aaa", None),
+    ("(?:a+)a", "aaa", Some((0, 3))),
+    ("(?>a|ab)c", "abc", None),
+    ("(?>ab|a)c", "abc", Some((0, 3))),
+    ("(?>a*?)b", "aab", Some((2, 3))),
+    // The whole pattern is still retried at later positions.
+    ("(?>x+)y", "xxz xy", Some((4, 6))),
+    // Possessive quantifiers take all they can and give none of it back.
+    ("a*+a", "aaa", None),
+    ("a*+b", "aaab", Some((0, 4))),
+    ("a*+", "", Some((0, 0))),
+    ("a++a", "aaa", None),
+    ("a++", "baa", Some((1, 3))),
+    ("a?+a", "a", None),
+    ("a?+a", "aa", Some((0, 2))),
+    ("\"[^\"]*+\"", "say \"hi\"", Some((4, 8))),
+    ("(?:ab)++b", "ababb", Some((0, 5))),
+];
+
+#[test]
+fn backtracking() {
+    for (pattern, haystack, expected) in CASES {
+        let nfa = backtrack::compile(pattern).unwrap();
+        assert_eq!(
+            backtrack::find(&nfa, haystack),
+            expected,
+            "{} on {:?}",
+            pattern,
+            haystack
+        );
+    }
+}
+
+#[test]
+fn no_catastrophic_backtracking() {
+    let haystack = format!("{}!", "a".repeat(30));
+    for pattern in ["^(?>(a+)+)$", "^(?:a++)+$", "^(a+)++$"] {
+        let nfa = backtrack::compile(pattern).unwrap();
+        assert!(!backtrack::is_match(&nfa, &haystack), "{}", pattern);
+    }
+}
+
+#[test]
+fn rejected_by_automata() {
+    let cases = [
+        ("x(?>a)", 1..4),
+        ("xa*+", 3..4),
+        ("xa++", 3..4),
+        ("xa?+", 3..4),
+    ];
+    for (pattern, span) in cases {
+        let error = infix_to_nfa(pattern).err().unwrap();
+        assert!(
+            matches!(error.kind(), ErrorKind::Unsupported(_)),
+            "{}",
+            pattern
+        );
+        assert_eq!(error.char_span(), span, "{}", pattern);
+
+        let error = create_matcher(pattern).err().unwrap();
+        assert!(matches!(error.kind(), ErrorKind::Unsupported(_)));
+        let error = regex0::regex::compile(pattern.to_string()).err().unwrap();
+        assert!(matches!(error.kind(), ErrorKind::Unsupported(_)));
+        let error = pda::compile(pattern).err().unwrap();
+        assert!(matches!(error.kind(), ErrorKind::Unsupported(_)));
+    }
+}
@@ -0,0 +1,75 @@
+//! Backreferences `\N` and `\k<name>`, which match the text their group
+//! captured and which only the backtracking engine can follow.
+
+use regex::error::ErrorKind;
+use regex::regex1::backtrack::{captures, compile};
+use regex::regex1::nfa::infix_to_nfa;
+
+// The byte spans of every group, group 0 first, if the pattern matches.
+type Captures = Option<Vec<Option<(usize, usize)>>>;
+
+#[test]
+fn backreferences() {
+    let cases: [(&str, &str, Captures); 10] = [
+        (
+            "(a|b)\\1",
+            "xaby bb",
+            Some(vec![Some((5, 7)), Some((5, 6))]),
+        ),
+        ("(a*)\\1", "aaaa", Some(vec![Some((0, 4)), Some((0, 2))])),
+        (
+            "(a)(b)\\2\\1",
+            "abba",
+            Some(vec![Some((0, 4)), Some((0, 1)), Some((1, 2))]),
+        ),
+        (
+            "(?<x>a+)-\\k<x>",
+            "aa-a",
+            Some(vec![Some((1, 4)), Some((1, 2))]),
+        ),
+        (
+            "(?P<n>.)\\k<n>",
+            "xyy",
+            Some(vec![Some((1, 3)), Some((1, 2))]),
+        ),
+        ("(?i)(a)\\1", "aA", Some(vec![Some((0, 2)), Some((0, 1))])),
+        // A group that has not captured anything matches nothing.
+        ("(a)?b\\1", "b", None),
+        ("(a)|b\\1", "b", None),
+        ("\\1(a)", "aa", None),
+        ("(a\\1)", "aa", None),
+    ];
+    for (pattern, haystack, expected) in cases {
+        let nfa = compile(pattern).unwrap();
+        assert_eq!(captures(&nfa, haystack), expected, "{}", pattern);
+    }
+}
+
+#[test]
+fn undefined_groups() {
+    let cases = [
+        (
+            "(a)\\2",
+            ErrorKind::UndefinedBackreference("\\2".to_string()),
+            3..5,
+        ),
+        (
+            "\\k<n>(?<n>a)",
+            ErrorKind::UndefinedBackreference("n".to_string()),
+            0..5,
+        ),
+        ("\\k<n", ErrorKind::UnterminatedGroupName, 3..4),
+    ];
+    for (pattern, kind, span) in cases {
+        let error = compile(pattern).err().unwrap();
+        assert_eq!(error.kind(), &kind, "{}", pattern);
+        assert_eq!(error.char_span(), span, "{}", pattern);
+    }
+}
+
+#[test]
+fn automata_reject_backreferences() {
+    let error = infix_to_nfa("(a)\\1").err().unwrap();
+    assert!(matches!(error.kind(), ErrorKind::Unsupported(_)));
+    assert_eq!(error.char_span(), 3..5);
+}
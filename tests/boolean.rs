@@ -0,0 +1,102 @@
+//! The `&` intersection and `~` complement operators of regex0.
+
+mod common;
+
+use regex::error::ErrorKind;
+use regex::regex0::regex::compile;
+
+#[test]
+fn identifiers_but_not_keywords() {
+    common::check_on(
+        common::REGEX0,
+        &["x", "if", "else", "iff", "els", "If", "", "x1"],
+        &[
+            (
+                "[a-z]+&~(if|else)",
+                [true, false, false, true, true, false, false, false],
+            ),
+            (
+                "[a-z][a-z0-9]*&~(if|else)",
+                [true, false, false, true, true, false, false, true],
+            ),
+            (
+                "(?i)[a-z]+&~(if|else)",
+                [true, false, false, true, true, false, false, false],
+            ),
+        ],
+    );
+}
+
+#[test]
+fn intersection() {
+    common::check_on(
+        common::REGEX0,
+        &["", "a", "ab", "abc", "aab", "bb"],
+        &[
+            ("a*b*&(ab)*", [true, false, true, false, false, false]),
+            (".*b&a.*", [false, false, true, false, true, false]),
+            ("a+&b+", [false, false, false, false, false, false]),
+            ("&", [true, false, false, false, false, false]),
+            ("a&", [false, false, false, false, false, false]),
+            (".*&.*&a.", [false, false, true, false, false, false]),
+        ],
+    );
+}
+
+#[test]
+fn complement() {
+    common::check_on(
+        common::REGEX0,
+        &["", "a", "aa", "b", "ab", "ba"],
+        &[
+            ("~a", [true, false, true, true, true, true]),
+            ("~(.*a.*)", [true, false, false, true, false, false]),
+            ("~~a", [false, true, false, false, false, false]),
+            ("~(.*)", [false, false, false, false, false, false]),
+            ("~()", [false, true, true, true, true, true]),
+        ],
+    );
+}
+
+#[test]
+fn precedence() {
+    common::check_on(
+        common::REGEX0,
+        &["", "a", "b", "ab", "aa", "bb", "ba"],
+        &[
+            // `&` binds looser than concatenation and tighter than `|`.
+            ("ab&a.", [false, false, false, true, false, false, false]),
+            ("a|b&.", [false, true, true, false, false, false, false]),
+            ("b&.|a", [false, true, true, false, false, false, false]),
+            ("(a|b)&a", [false, true, false, false, false, false, false]),
+            // `~` binds looser than quantifiers and tighter than
+            // concatenation.
+            ("~a*", [false, false, true, true, false, true, true]),
+            ("~ab", [false, false, true, false, false, true, false]),
+            ("~(ab)", [true, true, true, false, true, true, true]),
+        ],
+    );
+}
+
+#[test]
+fn assertions_inside_operators() {
+    // The span is that of the operator and its operands.
+    let cases = [
+        ("^a&a", 0..4),
+        ("a&a$", 0..4),
+        ("~\\bab", 0..3),
+        ("x(~(?:a\\b))", 2..10),
+    ];
+    for (pattern, span) in cases {
+        let error = compile(pattern.to_string()).err().unwrap();
+        assert_eq!(
+            error.kind(),
+            &ErrorKind::Unsupported("assertions cannot be used inside `&` and `~`".to_string()),
+            "{}",
+            pattern
+        );
+        assert_eq!(error.char_span(), span, "{}", pattern);
+    }
+    // Outside them, assertions are fine.
+    common::check_on(common::REGEX0, &["a", "b"], &[("^(a&.)$", [true, false])]);
+}
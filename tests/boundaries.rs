@@ -0,0 +1,57 @@
+//! Word boundaries `\b` and `\B`, with ASCII word characters by default and
+//! Unicode ones with the `unicode` flag.
+
+mod common;
+
+use regex::flags::Flags;
+use regex::regex1::nfa::{find, infix_to_nfa_with_flags};
+
+const HAYSTACKS: [&str; 7] = ["", "a", "a!", "!a", "ab", "é", "aé"];
+
+const MATRIX: [(&str, [bool; 7]); 10] = [
+    ("\\b", [false, false, false, false, false, false, false]),
+    ("\\B", [true, false, false, false, false, false, false]),
+    ("\\ba\\b", [false, true, false, false, false, false, false]),
+    ("a\\b.*", [false, true, true, false, false, false, true]),
+    ("a\\B.*", [false, false, false, false, true, false, false]),
+    (".*\\ba", [false, true, false, true, false, false, false]),
+    ("\\b.+\\b", [false, true, false, false, true, false, false]),
+    ("\\B.\\B", [false, false, false, false, false, true, false]),
+    (
+        "(?u)\\b.+\\b",
+        [false, true, false, false, true, true, true],
+    ),
+    (
+        "(?u)a\\Bé",
+        [false, false, false, false, false, false, true],
+    ),
+];
+
+#[test]
+fn every_front_end() {
+    common::check_extended(&HAYSTACKS, &MATRIX);
+}
+
+#[test]
+fn whole_words() {
+    common::check_find(&[
+        ("\\bfoo\\b", "a foo b", Some((2, 5))),
+        ("\\bfoo\\b", "foobar foo", Some((7, 10))),
+        ("\\bfoo\\b", "foo_bar", None),
+        ("\\Boo\\B", "foob", Some((1, 3))),
+        ("\\b.", " é a", Some((4, 5))),
+        ("\\bé", "aé é", Some((1, 3))),
+        ("(?u)\\bé", "aé é", Some((4, 6))),
+    ]);
+}
+
+#[test]
+fn unicode_flag() {
+    let mut flags = Flags::new();
+    flags.unicode = true;
+    let find =
+        |pattern, haystack| find(&infix_to_nfa_with_flags(pattern, flags).unwrap(), haystack);
+    assert_eq!(find("\\b.", " é a"), Some((1, 3)));
+    assert_eq!(find("\\b.+\\b", "é"), Some((0, 2)));
+    assert_eq!(find("a\\B.", "aé"), Some((0, 3)));
+}
@@ -0,0 +1,85 @@
+//! Case-insensitive matching with the `i` flag, inline or scoped to a group,
+//! where each character matches every character linked to it by the simple
+//! case mappings, directly or not.
+
+mod common;
+
+use regex::error::ErrorKind;
+use regex::regex1::nfa::{infix_to_nfa, recognize};
+use regex::{regex0, regex1};
+
+const HAYSTACKS: [&str; 6] = ["ab", "AB", "Ab", "aB", "k", "K"];
+
+const MATRIX: [(&str, [bool; 6]); 7] = [
+    ("(?i)ab", [true, true, true, true, false, false]),
+    ("(?i:a)b", [true, false, true, false, false, false]),
+    ("(?i)a(?-i)b", [true, false, true, false, false, false]),
+    ("a(?i)b", [true, false, false, true, false, false]),
+    ("(?i)k", [false, false, false, false, true, true]),
+    ("(?i)[j-l]", [false, false, false, false, true, true]),
+    ("(?i)[^k]", [false, false, false, false, false, false]),
+];
+
+#[test]
+fn inline_flags() {
+    common::check_extended(&HAYSTACKS, &MATRIX);
+}
+
+#[test]
+fn invalid_flags() {
+    let cases = [
+        ("(?q)a", ErrorKind::UnknownGroupFlag('q'), 2..3),
+        ("(?i-q:a)", ErrorKind::UnknownGroupFlag('q'), 4..5),
+    ];
+    for (pattern, kind, span) in cases {
+        let error = infix_to_nfa(pattern).err().unwrap();
+        assert_eq!(error.kind(), &kind, "{}", pattern);
+        assert_eq!(error.char_span(), span, "{}", pattern);
+    }
+}
+
+fn is_match(pattern: &str, haystack: &str) -> bool {
+    recognize(&infix_to_nfa(pattern).unwrap(), haystack)
+}
+
+#[test]
+fn case_orbits() {
+    for haystack in ["k", "K", "\u{212A}"] {
+        assert!(is_match("(?i)k", haystack));
+        assert!(is_match("(?i)K", haystack));
+        assert!(is_match("(?i)\u{212A}", haystack));
+        assert!(is_match("(?i)[j-l]", haystack));
+        assert!(!is_match("(?i)[^k]", haystack));
+    }
+    for haystack in ["s", "S", "\u{17F}"] {
+        assert!(is_match("(?i)s", haystack));
+        assert!(is_match("(?i)\u{17F}", haystack));
+    }
+    assert!(is_match("(?i)ß", "ẞ"));
+    assert!(is_match("(?i)ẞ", "ß"));
+    assert!(is_match("(?i)σ", "Σ"));
+    assert!(is_match("(?i)σ", "ς"));
+    assert!(!is_match("k", "\u{212A}"));
+    assert!(!is_match("(?i)k", "q"));
+}
+
+#[test]
+fn every_front_end() {
+    let mut nfa = regex0::regex::compile("(?i)kelvin".to_string()).unwrap();
+    assert!(nfa.match_regex("\u{212A}ELVIN"));
+    assert!(recognize(
+        &regex1::create_matcher("(?i)[a-z]+").unwrap(),
+        "Hello\u{212A}"
+    ));
+    let nfa = regex1::backtrack::compile("(?i)(k)\\1").unwrap();
+    assert!(regex1::backtrack::is_match(&nfa, "\u{212A}K"));
+}
+
+#[test]
+fn large_classes() {
+    assert!(is_match("(?i)[\\W]", "!"));
+    assert!(!is_match("(?i)[\\W]", "a"));
+    assert!(is_match("(?iu)[^\\w]+", " !—"));
+    assert!(!is_match("(?iu)[^\\w]", "É"));
+    assert!(is_match("(?i)[\\x00-\\x{10FFFF}]", "\u{10FFFF}"));
+}
@@ -0,0 +1,81 @@
+//! Bracket classes: ranges, negation, escapes and metacharacters taken
+//! literally inside them.
+
+mod common;
+
+use regex::error::ErrorKind;
+use regex::regex1::nfa::infix_to_nfa;
+
+const HAYSTACKS: [&str; 8] = ["a", "q", "Z", "7", "_", "-", "]", "\""];
+
+const MATRIX: [(&str, [bool; 8]); 11] = [
+    (
+        "[a-z]",
+        [true, true, false, false, false, false, false, false],
+    ),
+    (
+        "[a-z0-9_]",
+        [true, true, false, true, true, false, false, false],
+    ),
+    (
+        "[A-Za-z]",
+        [true, true, true, false, false, false, false, false],
+    ),
+    ("[^\"]", [true, true, true, true, true, true, true, false]),
+    ("[^a-z]", [false, false, true, true, true, true, true, true]),
+    (
+        "[\\]\\-]",
+        [false, false, false, false, false, true, true, false],
+    ),
+    (
+        "[a\\-z]",
+        [true, false, false, false, false, true, false, false],
+    ),
+    // A `-` first or last is literal.
+    (
+        "[a-]",
+        [true, false, false, false, false, true, false, false],
+    ),
+    (
+        "[-a]",
+        [true, false, false, false, false, true, false, false],
+    ),
+    ("[^-]", [true, true, true, true, true, false, true, true]),
+    (
+        "[a-c]+",
+        [true, false, false, false, false, false, false, false],
+    ),
+];
+
+#[test]
+fn every_front_end() {
+    common::check_extended(&HAYSTACKS, &MATRIX);
+}
+
+#[test]
+fn metacharacters_are_literal() {
+    let metas = ".*+?(){}|^$";
+    common::check_extended(
+        &[metas, ".", "a"],
+        &[
+            ("[.*+?(){}|^$]+", [true, true, false]),
+            ("[^.*+?(){}|^$]", [false, false, true]),
+            ("[.]", [false, true, false]),
+        ],
+    );
+}
+
+#[test]
+fn invalid_classes() {
+    let cases = [
+        ("[z-a]", ErrorKind::InvalidClassRange, 1..4),
+        ("x[a", ErrorKind::UnterminatedClass, 1..3),
+        ("[]", ErrorKind::EmptyClass, 0..1),
+        ("[^]", ErrorKind::EmptyClass, 0..2),
+    ];
+    for (pattern, kind, span) in cases {
+        let error = infix_to_nfa(pattern).err().unwrap();
+        assert_eq!(error.kind(), &kind, "{}", pattern);
+        assert_eq!(error.char_span(), span, "{}", pattern);
+    }
+}
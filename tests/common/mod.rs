@@ -0,0 +1,140 @@
+//! The harness of the tests running the front ends on the same patterns.
+
+#![allow(dead_code)]
+
+use regex::regex1::backtrack;
+use regex::regex1::nfa::{captures, infix_to_nfa, recognize};
+use regex::{pda, regex0, regex1, regex2};
+
+/// Whether a pattern matches the whole of a haystack.
+pub type IsMatch = fn(&str, &str) -> bool;
+
+/// The NFA of `regex1`, built from the tree of its parser.
+pub const REGEX1: (&str, IsMatch) = ("regex1 parser", |pattern, haystack| {
+    recognize(&infix_to_nfa(pattern).unwrap(), haystack)
+});
+
+/// The NFA of `regex0`.
+pub const REGEX0: (&str, IsMatch) = ("regex0", |pattern, haystack| {
+    let mut nfa = regex0::regex::compile(pattern.to_string()).unwrap();
+    nfa.match_regex(haystack)
+});
+
+/// The NFA of `regex1`, built by `create_matcher`.
+pub const MATCHER: (&str, IsMatch) = ("regex1 matcher", |pattern, haystack| {
+    recognize(&regex1::create_matcher(pattern).unwrap(), haystack)
+});
+
+/// The backtracking engine of `regex1`.
+pub const BACKTRACK: (&str, IsMatch) = ("regex1 backtrack", |pattern, haystack| {
+    let nfa = backtrack::compile(pattern).unwrap();
+    backtrack::is_full_match(&nfa, haystack)
+});
+
+/// The pushdown automaton.
+pub const PDA: (&str, IsMatch) = ("pda", |pattern, haystack| {
+    pda::is_full_match(&pda::compile(pattern).unwrap(), haystack)
+});
+
+/// The front ends reading the syntax of `regex1`, by name. That of `regex0` adds
+/// to it.
+pub const EXTENDED: [(&str, IsMatch); 5] = [REGEX0, REGEX1, MATCHER, BACKTRACK, PDA];
+
+/// The front end reading the `Basic` syntax.
+pub const BASIC: (&str, IsMatch) = ("regex2", |pattern, haystack| {
+    regex2::compile(pattern).unwrap().is_full_match(haystack)
+});
+
+/// Checks every front end on `matrix`: each pattern, with whether it matches
+/// the whole of each of `haystacks`.
+pub fn check<const N: usize>(haystacks: &[&str; N], matrix: &[(&str, [bool; N])]) {
+    check_extended(haystacks, matrix);
+    check_on(BASIC, haystacks, matrix);
+}
+
+/// Like `check`, for patterns in the syntax of `regex1`.
+pub fn check_extended<const N: usize>(haystacks: &[&str; N], matrix: &[(&str, [bool; N])]) {
+    for engine in EXTENDED {
+        check_on(engine, haystacks, matrix);
+    }
+}
+
+/// Like `check_extended`, for patterns only the engines of `regex1` support.
+pub fn check_regex1<const N: usize>(haystacks: &[&str; N], matrix: &[(&str, [bool; N])]) {
+    for engine in [REGEX1, MATCHER, BACKTRACK] {
+        check_on(engine, haystacks, matrix);
+    }
+}
+
+/// Checks `engine` on `matrix`: each pattern, with whether it matches the
+/// whole of each of `haystacks`.
+pub fn check_on<const N: usize>(
+    (engine, is_match): (&str, IsMatch),
+    haystacks: &[&str; N],
+    matrix: &[(&str, [bool; N])],
+) {
+    for (pattern, expected) in matrix {
+        for (haystack, expected) in haystacks.iter().zip(expected) {
+            assert_eq!(
+                is_match(pattern, haystack),
+                *expected,
+                "{} on pattern {:?} and haystack {:?}",
+                engine,
+                pattern,
+                haystack
+            );
+        }
+    }
+}
+
+/// The byte span of a match, if any.
+pub type Match = Option<(usize, usize)>;
+
+/// The spans of every group of a match, group 0 first, if any.
+pub type Captures = Option<Vec<Match>>;
+
+/// The groups of the leftmost match of a pattern in a haystack.
+pub type Search = fn(&str, &str) -> Captures;
+
+/// The engines searching for a match, by name.
+pub const SEARCHING: [(&str, Search); 2] = [
+    ("regex1 pike vm", |pattern, haystack| {
+        captures(&infix_to_nfa(pattern).unwrap(), haystack)
+    }),
+    ("regex1 backtracker", |pattern, haystack| {
+        backtrack::captures(&backtrack::compile(pattern).unwrap(), haystack)
+    }),
+];
+
+/// Checks every searching engine on `cases`: each pattern and haystack, with
+/// the match found.
+pub fn check_find(cases: &[(&str, &str, Match)]) {
+    for (engine, search) in SEARCHING {
+        for (pattern, haystack, expected) in cases {
+            assert_eq!(
+                search(pattern, haystack).and_then(|groups| groups[0]),
+                *expected,
+                "{} on pattern {:?} and haystack {:?}",
+                engine,
+                pattern,
+                haystack
+            );
+        }
+    }
+}
+
+/// Like `check_find`, with the span of every group.
+pub fn check_captures(cases: &[(&str, &str, Captures)]) {
+    for (engine, search) in SEARCHING {
+        for (pattern, haystack, expected) in cases {
+            assert_eq!(
+                search(pattern, haystack),
+                *expected,
+                "{} on pattern {:?} and haystack {:?}",
+                engine,
+                pattern,
+                haystack
+            );
+        }
+    }
+}
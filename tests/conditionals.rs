@@ -0,0 +1,58 @@
+//! Conditionals, `(?(condition)yes|no)`, matched by the backtracking engine.
+
+use regex::error::ErrorKind;
+use regex::regex1::backtrack;
+use regex::regex1::nfa::infix_to_nfa;
+
+fn find(pattern: &str, haystack: &str) -> Option<(usize, usize)> {
+    backtrack::find(&backtrack::compile(pattern).unwrap(), haystack)
+}
+
+#[test]
+fn group_conditions() {
+    let pattern = "^(<)?a(?(1)>|b)$";
+    assert_eq!(find(pattern, "<a>"), Some((0, 3)));
+    assert_eq!(find(pattern, "ab"), Some((0, 2)));
+    assert_eq!(find(pattern, "<ab"), None);
+    assert_eq!(find(pattern, "a>"), None);
+    assert_eq!(find("^(?<q>\")?x(?(<q>)\")$", "\"x\""), Some((0, 3)));
+}
+
+#[test]
+fn lookaround_conditions() {
+    let pattern = "(?(?=\\d)\\d{3}|[a-z]{2})";
+    assert_eq!(find(pattern, "123"), Some((0, 3)));
+    assert_eq!(find(pattern, "ab"), Some((0, 2)));
+    assert_eq!(find("^(?(?<!a)b|c)", "b"), Some((0, 1)));
+}
+
+#[test]
+fn invalid_conditions() {
+    for (pattern, span) in [("(?(0)a|b)", 3..4), ("(?(x)a)", 3..4), ("(?(00)a)", 3..5)] {
+        let error = backtrack::compile(pattern).err().unwrap();
+        assert_eq!(error.kind(), &ErrorKind::InvalidCondition, "{}", pattern);
+        assert_eq!(error.char_span(), span, "{}", pattern);
+    }
+
+    let cases = [("(a)(?(2)a)", "2", 6..7), ("(?(<n>)a)", "n", 2..6)];
+    for (pattern, group, span) in cases {
+        let error = backtrack::compile(pattern).err().unwrap();
+        assert_eq!(
+            error.kind(),
+            &ErrorKind::UndefinedCondition(group.to_string()),
+            "{}",
+            pattern
+        );
+        assert_eq!(error.char_span(), span, "{}", pattern);
+    }
+
+    let error = backtrack::compile("(?(1)a|b|c)").err().unwrap();
+    assert_eq!(error.kind(), &ErrorKind::TooManyBranches);
+}
+
+#[test]
+fn rejected_by_automata() {
+    let error = infix_to_nfa("(a)(?(1)b)").err().unwrap();
+    assert!(matches!(error.kind(), ErrorKind::Unsupported(_)));
+    assert_eq!(error.char_span(), 3..8);
+}
@@ -0,0 +1,44 @@
+//! `.` matches any character but `\n`, unless in dot-all mode, and `\.` a dot.
+
+mod common;
+
+use regex::flags::Flags;
+use regex::regex1::nfa::{infix_to_nfa_with_flags, recognize};
+use regex::{regex0, regex1};
+
+const HAYSTACKS: [&str; 6] = ["a", ".", "\n", "é", "a.b", "a\nb"];
+
+const MATRIX: [(&str, [bool; 6]); 7] = [
+    (".", [true, true, false, true, false, false]),
+    ("(?s).", [true, true, true, true, false, false]),
+    ("a(?s:.)b", [false, false, false, false, true, true]),
+    ("(?s)a(?-s:.)b", [false, false, false, false, true, false]),
+    ("\\.", [false, true, false, false, false, false]),
+    ("a\\.b", [false, false, false, false, true, false]),
+    ("...?", [false, false, false, false, true, false]),
+];
+
+#[test]
+fn every_front_end() {
+    common::check_extended(&HAYSTACKS, &MATRIX);
+}
+
+#[test]
+fn dot_all_flag() {
+    let flags = Flags {
+        dot_all: true,
+        ..Flags::new()
+    };
+    for nfa in [
+        infix_to_nfa_with_flags("a.b", flags).unwrap(),
+        regex1::create_matcher_with_flags("a.b", flags).unwrap(),
+    ] {
+        assert!(recognize(&nfa, "a\nb"));
+        assert!(recognize(&nfa, "a.b"));
+    }
+    let mut nfa = regex0::regex::compile_with_flags("a.b".to_string(), flags).unwrap();
+    assert!(nfa.match_regex("a\nb"));
+
+    let nfa = regex1::create_matcher_with_flags("a(?-s).b", flags).unwrap();
+    assert!(!recognize(&nfa, "a\nb"));
+}
@@ -0,0 +1,24 @@
+//! Empty patterns, alternatives and groups match the empty string in every
+//! front end.
+
+mod common;
+
+const HAYSTACKS: [&str; 4] = ["", "a", "b", "ab"];
+
+const MATRIX: [(&str, [bool; 4]); 10] = [
+    ("", [true, false, false, false]),
+    ("a|", [true, true, false, false]),
+    ("|a", [true, true, false, false]),
+    ("()", [true, false, false, false]),
+    ("(|b)", [true, false, true, false]),
+    ("a||b", [true, true, true, false]),
+    ("(())", [true, false, false, false]),
+    ("()*", [true, false, false, false]),
+    ("a()b", [false, false, false, true]),
+    ("(a|)b", [false, false, true, true]),
+];
+
+#[test]
+fn every_front_end() {
+    common::check(&HAYSTACKS, &MATRIX);
+}
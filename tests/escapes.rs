@@ -0,0 +1,129 @@
+//! Escape sequences for control characters, code points in hex and octal, and
+//! escaped metacharacters, which every front end reads alike.
+
+mod common;
+
+use regex::error::ErrorKind;
+use regex::regex1::create_matcher;
+use regex::regex1::nfa::infix_to_nfa;
+
+const HAYSTACKS: [&str; 7] = ["\n", "\t\r", "A", "é", "😀", "\0", "\n1"];
+
+const MATRIX: [(&str, [bool; 7]); 13] = [
+    ("\\n", [true, false, false, false, false, false, false]),
+    ("\\t\\r", [false, true, false, false, false, false, false]),
+    ("\\x41", [false, false, true, false, false, false, false]),
+    ("\\x{41}", [false, false, true, false, false, false, false]),
+    ("\\x{E9}", [false, false, false, true, false, false, false]),
+    ("\\u00e9", [false, false, false, true, false, false, false]),
+    (
+        "\\u{1F600}",
+        [false, false, false, false, true, false, false],
+    ),
+    (
+        "\\x{0001F600}",
+        [false, false, false, false, true, false, false],
+    ),
+    ("\\0", [false, false, false, false, false, true, false]),
+    ("\\012", [true, false, false, false, false, false, false]),
+    // At most three octal digits, so this is `\012` then `1`.
+    ("\\0121", [false, false, false, false, false, false, true]),
+    ("\\o{101}", [false, false, true, false, false, false, false]),
+    ("\\o{351}", [false, false, false, true, false, false, false]),
+];
+
+#[test]
+fn every_front_end() {
+    common::check(&HAYSTACKS, &MATRIX);
+}
+
+#[test]
+fn escapes_in_classes() {
+    common::check_extended(
+        &["\t", "A", "b", "-"],
+        &[
+            ("[\\t\\x41]", [true, true, false, false]),
+            ("[\\x41-\\x{5A}]", [false, true, false, false]),
+            ("[^\\u0041\\o{142}]", [true, false, false, true]),
+            ("[a\\-z]", [false, false, false, true]),
+        ],
+    );
+}
+
+#[test]
+fn escaped_metacharacters() {
+    let haystacks = ["a*b", "aab", "a.b", "a|b", "(a)", "a\\b", "[a]", "{a}"];
+    common::check_extended(
+        &haystacks,
+        &[
+            (
+                "a\\*b",
+                [true, false, false, false, false, false, false, false],
+            ),
+            (
+                "a\\.b",
+                [false, false, true, false, false, false, false, false],
+            ),
+            (
+                "a\\|b",
+                [false, false, false, true, false, false, false, false],
+            ),
+            (
+                "\\(a\\)",
+                [false, false, false, false, true, false, false, false],
+            ),
+            (
+                "a\\\\b",
+                [false, false, false, false, false, true, false, false],
+            ),
+            (
+                "\\[a\\]",
+                [false, false, false, false, false, false, true, false],
+            ),
+            (
+                "\\{a\\}",
+                [false, false, false, false, false, false, false, true],
+            ),
+            (
+                "a\\+b",
+                [false, false, false, false, false, false, false, false],
+            ),
+        ],
+    );
+}
+
+#[test]
+fn invalid_escapes() {
+    let cases = [
+        (
+            "\\x4",
+            ErrorKind::InvalidEscape("expected 2 digits".to_string()),
+        ),
+        (
+            "\\u12",
+            ErrorKind::InvalidEscape("expected 4 digits".to_string()),
+        ),
+        ("\\x{}", ErrorKind::InvalidEscape("{".to_string())),
+        ("\\x{41", ErrorKind::InvalidEscape("{41".to_string())),
+        (
+            "\\x{123456789}",
+            ErrorKind::InvalidEscape("{123456789".to_string()),
+        ),
+        ("\\x{4G}", ErrorKind::InvalidEscape("\\x4G".to_string())),
+        (
+            "\\o101",
+            ErrorKind::InvalidEscape("expected '{'".to_string()),
+        ),
+        ("\\o{8}", ErrorKind::InvalidEscape("\\o8".to_string())),
+        ("\\x{D800}", ErrorKind::InvalidCodePoint(0xD800)),
+        ("\\u{110000}", ErrorKind::InvalidCodePoint(0x11_0000)),
+        ("\\", ErrorKind::TrailingBackslash),
+        ("\\q", ErrorKind::UnknownEscape('q')),
+    ];
+    for (pattern, kind) in cases {
+        let error = infix_to_nfa(pattern).err().unwrap();
+        assert_eq!(error.kind(), &kind, "{}", pattern);
+        let error = create_matcher(pattern).err().unwrap();
+        assert_eq!(error.kind(), &kind, "{}", pattern);
+    }
+}
@@ -0,0 +1,94 @@
+//! Capturing, non-capturing and named groups, and the spans they capture.
+
+mod common;
+
+use common::Captures;
+use regex::error::ErrorKind;
+use regex::regex1::nfa::infix_to_nfa;
+
+// Each pattern and haystack, with the groups captured.
+fn cases() -> Vec<(&'static str, &'static str, Captures)> {
+    vec![
+        (
+            "(a)(b)",
+            "ab",
+            Some(vec![Some((0, 2)), Some((0, 1)), Some((1, 2))]),
+        ),
+        (
+            "x(a(b))",
+            "zxab",
+            Some(vec![Some((1, 4)), Some((2, 4)), Some((3, 4))]),
+        ),
+        ("(?:a)(b)", "ab", Some(vec![Some((0, 2)), Some((1, 2))])),
+        ("(?:ab)+", "abab", Some(vec![Some((0, 4))])),
+        // A repeated group holds its last iteration.
+        ("(a|b)+", "ab", Some(vec![Some((0, 2)), Some((1, 2))])),
+        (
+            "(?P<x>a)(?<y>b)",
+            "ab",
+            Some(vec![Some((0, 2)), Some((0, 1)), Some((1, 2))]),
+        ),
+        // Groups that do not take part in the match capture nothing.
+        ("(a)|(b)", "b", Some(vec![Some((0, 1)), None, Some((0, 1))])),
+        ("(a)?b", "b", Some(vec![Some((0, 1)), None])),
+        ("(a)*", "", Some(vec![Some((0, 0)), None])),
+        // An empty group still participates.
+        ("a()b", "ab", Some(vec![Some((0, 2)), Some((1, 1))])),
+        ("(a)", "b", None),
+    ]
+}
+
+#[test]
+fn capture_spans() {
+    common::check_captures(&cases());
+}
+
+#[test]
+fn group_names() {
+    let nfa = infix_to_nfa("(a)(?P<first>b)(?:c)(?<second>d)").unwrap();
+    assert_eq!(nfa.captures_len(), 4);
+    assert_eq!(
+        nfa.group_names(),
+        &[
+            None,
+            None,
+            Some("first".to_string()),
+            Some("second".to_string())
+        ]
+    );
+    assert_eq!(nfa.group_index("first"), Some(2));
+    assert_eq!(nfa.group_index("second"), Some(3));
+    assert_eq!(nfa.group_index("third"), None);
+
+    let nfa = infix_to_nfa("abc").unwrap();
+    assert_eq!(nfa.captures_len(), 1);
+    assert_eq!(nfa.group_index("abc"), None);
+}
+
+#[test]
+fn invalid_groups() {
+    let cases = [
+        (
+            "(?P<n>a)(?<n>b)",
+            ErrorKind::DuplicateGroupName("n".to_string()),
+            10..13,
+        ),
+        (
+            "(?<n>a)(?P<n>b)",
+            ErrorKind::DuplicateGroupName("n".to_string()),
+            10..13,
+        ),
+        (
+            "(?<1a>b)",
+            ErrorKind::InvalidGroupName("1a".to_string()),
+            3..5,
+        ),
+        ("(?<n", ErrorKind::UnterminatedGroupName, 3..4),
+        ("(a", ErrorKind::UnbalancedParenthesis, 0..1),
+    ];
+    for (pattern, kind, span) in cases {
+        let error = infix_to_nfa(pattern).err().unwrap();
+        assert_eq!(error.kind(), &kind, "{}", pattern);
+        assert_eq!(error.char_span(), span, "{}", pattern);
+    }
+}
@@ -0,0 +1,44 @@
+//! Lazy quantifiers and leftmost-first searches.
+
+mod common;
+
+use common::Match;
+
+// Each pattern and haystack, with the match found.
+const CASES: [(&str, &str, Match); 16] = [
+    ("<.+?>", "<a><b>", Some((0, 3))),
+    ("<.+>", "<a><b>", Some((0, 6))),
+    ("a*?", "aaa", Some((0, 0))),
+    ("a*?b", "aaab", Some((0, 4))),
+    ("a+?", "aaa", Some((0, 1))),
+    ("a??", "a", Some((0, 0))),
+    ("a??b", "ab", Some((0, 2))),
+    ("a{2,4}?", "aaaa", Some((0, 2))),
+    ("a{2,}?", "aaaa", Some((0, 2))),
+    ("a{2,4}", "aaaa", Some((0, 4))),
+    ("(a|ab)(c|bcd)", "abcd", Some((0, 4))),
+    // The first alternative that matches wins, not the longest.
+    ("a|ab", "ab", Some((0, 1))),
+    ("ab|a", "ab", Some((0, 2))),
+    ("(?:a|ab)c", "abc", Some((0, 3))),
+    // The leftmost match wins, lazy or not.
+    ("b+?", "abbb", Some((1, 2))),
+    ("x*?", "", Some((0, 0))),
+];
+
+#[test]
+fn leftmost_first() {
+    common::check_find(&CASES);
+}
+
+#[test]
+fn whole_matches() {
+    common::check_extended(
+        &["", "aa", "aaa"],
+        &[
+            ("a*?", [true, true, true]),
+            ("a{2}?", [false, true, false]),
+            ("a{2,}?", [false, true, true]),
+        ],
+    );
+}
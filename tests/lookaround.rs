@@ -0,0 +1,80 @@
+//! Lookahead and lookbehind, positive and negative.
+
+mod common;
+
+use common::Match;
+use regex::error::ErrorKind;
+use regex::regex1::nfa::{captures, infix_to_nfa};
+use regex::{pda, regex0};
+
+const PASSWORDS: [&str; 5] = ["abc", "abc1", "ABC1", "aBc1", "aB1"];
+
+// At least four chars, with a digit, a lowercase and an uppercase letter.
+const POLICY: [(&str, [bool; 5]); 4] = [
+    ("(?=.*[0-9]).{4,}", [false, true, true, true, false]),
+    (
+        "(?=.*[0-9])(?=.*[a-z]).{4,}",
+        [false, true, false, true, false],
+    ),
+    (
+        "(?=.*[0-9])(?=.*[a-z])(?=.*[A-Z]).{4,}",
+        [false, false, false, true, false],
+    ),
+    ("(?!.*[A-Z]).*", [true, true, false, false, false]),
+];
+
+#[test]
+fn password_policy() {
+    common::check_regex1(&PASSWORDS, &POLICY);
+}
+
+#[test]
+fn unsupported_engines() {
+    let pattern = "a(?=b)";
+    let error = regex0::regex::compile(pattern.to_string()).err().unwrap();
+    assert!(matches!(error.kind(), ErrorKind::Unsupported(_)));
+    let error = pda::compile(pattern).err().unwrap();
+    assert!(matches!(error.kind(), ErrorKind::Unsupported(_)));
+}
+
+// Each pattern and haystack, with the match found.
+const CASES: [(&str, &str, Match); 18] = [
+    ("foo(?=bar)", "foobaz foobar", Some((7, 10))),
+    ("foo(?!bar)", "foobar foobaz", Some((7, 10))),
+    ("(?<=\\$)[0-9]+", "12 $34", Some((4, 6))),
+    ("(?<!\\$)\\b[0-9]+", "$12 34", Some((4, 6))),
+    // Lookarounds match nothing themselves.
+    ("(?=a)", "ba", Some((1, 1))),
+    ("(?!a)", "ab", Some((1, 1))),
+    ("(?<=a)", "ba", Some((2, 2))),
+    ("(?<!a)", "ab", Some((0, 0))),
+    ("a(?=b)b", "ab", Some((0, 2))),
+    ("a(?!b)b", "ab", None),
+    // They may look beyond the start and the end of the match.
+    ("(?<=ab)c(?=de)", "abcde", Some((2, 3))),
+    // Lookbehinds of any length.
+    ("(?<=a.*)b", "xxb axxb", Some((7, 8))),
+    ("(?<!a.*)b", "axb", None),
+    ("(?<=^a*)b", "aab ab", Some((2, 3))),
+    ("(?<=a|bcd)e", "bcdae", Some((4, 5))),
+    // Lookarounds inside lookarounds.
+    ("a(?=b(?!c))", "abc ab", Some((4, 5))),
+    ("(?<=(?<!x)a)b", "xab ab", Some((5, 6))),
+    ("(?=(?<=a)b).", "ba ab", Some((4, 5))),
+];
+
+#[test]
+fn matches() {
+    common::check_find(&CASES);
+}
+
+#[test]
+fn groups_in_lookarounds() {
+    // Numbered as usual, but never reporting a span.
+    let nfa = infix_to_nfa("(?=(a))(a)").unwrap();
+    assert_eq!(nfa.captures_len(), 3);
+    assert_eq!(
+        captures(&nfa, "a"),
+        Some(vec![Some((0, 1)), None, Some((0, 1))])
+    );
+}
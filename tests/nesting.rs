@@ -0,0 +1,262 @@
+//! Long and deeply nested patterns are parsed and built without recursion, and
+//! nesting beyond the limit is an error rather than a stack overflow.
+
+use regex::ast::{Ast, AstKind, Group, GroupKind};
+use regex::error::ErrorKind;
+use regex::options::Options;
+use regex::regex1::nfa::{ast_to_nfa, infix_to_nfa, infix_to_nfa_with_options, recognize};
+use regex::regex1::parser::Parser;
+use regex::syntax::{Syntax, MAX_NESTING_LIMIT, NESTING_LIMIT};
+use regex::{pda, regex0, regex1, regex2};
+
+fn nested(depth: usize) -> String {
+    format!("{}a{}", "(".repeat(depth), ")".repeat(depth))
+}
+
+#[test]
+fn long_patterns() {
+    let literal = "a".repeat(10_000);
+    assert!(recognize(&infix_to_nfa(&literal).unwrap(), &literal));
+    assert!(regex0::regex::compile(literal.clone())
+        .unwrap()
+        .match_regex(&literal));
+
+    let alternation = vec!["a"; 10_000].join("|");
+    assert!(Parser::new(alternation.clone()).parse().is_ok());
+    assert!(regex0::regex::compile(alternation).is_ok());
+}
+
+// Epsilon closures follow chains as long as the pattern, here on the stack
+// size the main thread usually has.
+#[test]
+fn long_epsilon_chains() {
+    let pattern = "()".repeat(100_000);
+    let matched = std::thread::Builder::new()
+        .stack_size(8 << 20)
+        .spawn(move || {
+            [
+                recognize(&infix_to_nfa(&pattern).unwrap(), ""),
+                regex0::regex::compile(pattern.clone())
+                    .unwrap()
+                    .match_regex(""),
+                regex2::compile(&pattern).unwrap().is_full_match(""),
+            ]
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    assert_eq!(matched, [true; 3]);
+}
+
+// However many paths lead to a state, it is entered once per position.
+#[test]
+fn shared_epsilon_paths() {
+    let haystack = "a".repeat(1_000);
+    for pattern in ["(a|a)*", "((a|a)|(a|a))*", "(a?a?)*"] {
+        let nfa = infix_to_nfa(pattern).unwrap();
+        assert!(recognize(&nfa, &haystack), "{}", pattern);
+        assert!(!recognize(&nfa, &format!("{}b", haystack)), "{}", pattern);
+    }
+}
+
+#[test]
+fn nesting_up_to_the_limit() {
+    let pattern = nested(NESTING_LIMIT);
+    assert!(recognize(&infix_to_nfa(&pattern).unwrap(), "a"));
+    let nfa = regex1::backtrack::compile(&pattern).unwrap();
+    assert!(regex1::backtrack::is_match(&nfa, "a"));
+    assert!(pda::is_match(&pda::compile(&pattern).unwrap(), "a"));
+    assert!(regex0::regex::compile(pattern).unwrap().match_regex("a"));
+}
+
+#[test]
+fn nesting_beyond_the_limit() {
+    let pattern = nested(NESTING_LIMIT + 1);
+    let error = Parser::new(pattern.clone()).parse().err().unwrap();
+    assert_eq!(error.kind(), &ErrorKind::NestingTooDeep(NESTING_LIMIT));
+    assert_eq!(error.char_span(), NESTING_LIMIT..NESTING_LIMIT + 1);
+
+    let error = regex0::regex::compile(pattern).err().unwrap();
+    assert_eq!(error.kind(), &ErrorKind::NestingTooDeep(NESTING_LIMIT));
+    assert_eq!(error.char_span(), NESTING_LIMIT..NESTING_LIMIT + 1);
+}
+
+#[test]
+fn configured_limit() {
+    let parse = |pattern: &str| {
+        let mut parser = Parser::new(pattern.to_string());
+        parser.set_nesting_limit(2);
+        parser.parse().err().map(|error| error.kind().clone())
+    };
+    assert_eq!(parse("((a))(b)"), None);
+    assert_eq!(parse("(((a)))"), Some(ErrorKind::NestingTooDeep(2)));
+    assert_eq!(parse("([[a]])"), Some(ErrorKind::NestingTooDeep(2)));
+    assert_eq!(parse("(?(?=(a))b)"), None);
+    assert_eq!(parse("(?(?=((a)))b)"), Some(ErrorKind::NestingTooDeep(2)));
+
+    let parse = |pattern: &str| {
+        let mut parser = Parser::new(pattern.to_string());
+        parser.set_syntax(Syntax::Boolean);
+        parser.set_nesting_limit(2);
+        parser.parse().err().map(|error| error.kind().clone())
+    };
+    assert_eq!(parse("((a))(b)"), None);
+    assert_eq!(parse("(((a)))"), Some(ErrorKind::NestingTooDeep(2)));
+    assert_eq!(parse("~~a"), None);
+    assert_eq!(parse("~(a)"), None);
+    assert_eq!(parse("~(~a)"), Some(ErrorKind::NestingTooDeep(2)));
+    assert_eq!(parse("~~~a"), Some(ErrorKind::NestingTooDeep(2)));
+
+    let parse = |pattern: &str| {
+        let mut parser = Parser::new(pattern.to_string());
+        parser.set_syntax(Syntax::Basic);
+        parser.set_nesting_limit(2);
+        parser.parse().err().map(|error| error.kind().clone())
+    };
+    assert_eq!(parse("(a**)"), None);
+    assert_eq!(parse("a***"), None);
+    assert_eq!(parse("a****"), Some(ErrorKind::NestingTooDeep(2)));
+}
+
+#[test]
+fn limit_in_options() {
+    let options = Options {
+        nesting_limit: 2,
+        ..Options::new()
+    };
+    let kind = |error: regex::error::Error| error.kind().clone();
+    let expected = Some(ErrorKind::NestingTooDeep(2));
+
+    for (pattern, limited) in [("((a))", false), ("(((a)))", true)] {
+        let expected = if limited { expected.clone() } else { None };
+        assert_eq!(
+            infix_to_nfa_with_options(pattern, options).err().map(kind),
+            expected
+        );
+        assert_eq!(
+            regex1::create_matcher_with_options(pattern, options)
+                .err()
+                .map(kind),
+            expected
+        );
+        assert_eq!(
+            regex1::backtrack::compile_with_options(pattern, options)
+                .err()
+                .map(kind),
+            expected
+        );
+        assert_eq!(
+            regex0::regex::compile_with_options(pattern.to_string(), options)
+                .err()
+                .map(kind),
+            expected
+        );
+        assert_eq!(
+            pda::compile_with_options(pattern, options).err().map(kind),
+            expected
+        );
+        assert_eq!(
+            regex2::compile_with_options(pattern, options)
+                .err()
+                .map(kind),
+            expected
+        );
+    }
+
+    // Every front end takes patterns nested as deeply as the limit may be
+    // raised to.
+    let depth = MAX_NESTING_LIMIT;
+    let options = Options {
+        nesting_limit: depth,
+        ..Options::new()
+    };
+    let alternations = format!("{}a{}", "(b|".repeat(depth), ")".repeat(depth));
+    let classes = format!("{}a{}", "[".repeat(depth), "]".repeat(depth));
+    for pattern in [nested(depth), alternations.clone(), classes] {
+        let nfa = infix_to_nfa_with_options(&pattern, options).unwrap();
+        assert!(recognize(&nfa, "a"));
+        let nfa = regex1::create_matcher_with_options(&pattern, options).unwrap();
+        assert!(recognize(&nfa, "a"));
+        let nfa = regex1::backtrack::compile_with_options(&pattern, options).unwrap();
+        assert!(regex1::backtrack::is_match(&nfa, "a"));
+        let mut nfa = regex0::regex::compile_with_options(pattern.clone(), options).unwrap();
+        assert!(nfa.match_regex("a"));
+        let pda = pda::compile_with_options(&pattern, options).unwrap();
+        assert!(pda::is_match(&pda, "a"));
+    }
+    for pattern in [
+        nested(depth),
+        alternations,
+        format!("a{}", "*".repeat(depth - 1)),
+    ] {
+        let nfa = regex2::compile_with_options(&pattern, options).unwrap();
+        assert!(nfa.is_full_match("a"));
+    }
+    let lookarounds = format!("{}a{}a", "(?=".repeat(depth), ")".repeat(depth));
+    let nfa = infix_to_nfa_with_options(&lookarounds, options).unwrap();
+    assert!(recognize(&nfa, "a"));
+    for pattern in [
+        lookarounds,
+        format!("{}a{}", "(?>".repeat(depth), ")".repeat(depth)),
+    ] {
+        let nfa = regex1::backtrack::compile_with_options(&pattern, options).unwrap();
+        assert!(regex1::backtrack::is_match(&nfa, "a"));
+    }
+
+    // Higher limits are lowered to it rather than let the stack overflow.
+    let options = Options {
+        nesting_limit: 5_001,
+        ..Options::new()
+    };
+    let expected = Some(ErrorKind::NestingTooDeep(MAX_NESTING_LIMIT));
+    let pattern = nested(5_000);
+    assert_eq!(
+        infix_to_nfa_with_options(&pattern, options).err().map(kind),
+        expected
+    );
+    assert_eq!(
+        regex1::create_matcher_with_options(&pattern, options)
+            .err()
+            .map(kind),
+        expected
+    );
+    assert_eq!(
+        regex1::backtrack::compile_with_options(&pattern, options)
+            .err()
+            .map(kind),
+        expected
+    );
+    assert_eq!(
+        regex0::regex::compile_with_options(pattern.clone(), options)
+            .err()
+            .map(kind),
+        expected
+    );
+    assert_eq!(
+        pda::compile_with_options(&pattern, options).err().map(kind),
+        expected
+    );
+    assert_eq!(
+        regex2::compile_with_options(&pattern, options)
+            .err()
+            .map(kind),
+        expected
+    );
+    let mut parser = Parser::new(nested(100_000));
+    parser.set_nesting_limit(100_001);
+    assert_eq!(parser.parse().err().map(kind), expected);
+}
+
+// Trees are built into automata and dropped without recursion, however deep.
+#[test]
+fn deep_trees() {
+    let mut ast = Ast::new(AstKind::Literal('a'), 0..1);
+    for _ in 0..100_000 {
+        let group = Group {
+            kind: GroupKind::NonCapture,
+            ast: Box::new(ast),
+        };
+        ast = Ast::new(AstKind::Group(group), 0..1);
+    }
+    assert!(recognize(&ast_to_nfa("a", &ast).unwrap(), "a"));
+}
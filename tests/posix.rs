@@ -0,0 +1,54 @@
+//! POSIX classes and the set operations `&&`, `--` and `~~` in bracket
+//! classes.
+
+mod common;
+
+use regex::error::ErrorKind;
+use regex::regex1::nfa::infix_to_nfa;
+
+const HAYSTACKS: [&str; 6] = ["a", "b", "d", "z", "5", "!"];
+
+const MATRIX: [(&str, [bool; 6]); 12] = [
+    ("[[:alpha:]]", [true, true, true, true, false, false]),
+    ("[[:^digit:]]", [true, true, true, true, false, true]),
+    (
+        "[[:punct:][:digit:]]",
+        [false, false, false, false, true, true],
+    ),
+    ("[^[:alnum:]]", [false, false, false, false, false, true]),
+    ("[a-z&&[^aeiou]]", [false, true, true, true, false, false]),
+    ("[\\w--\\d]", [true, true, true, true, false, false]),
+    ("[a-c~~b-d]", [true, false, true, false, false, false]),
+    ("[^a-z--b-y]", [false, true, true, false, true, true]),
+    // The operators are left-associative.
+    ("[a-z--a-y--b]", [false, false, false, true, false, false]),
+    ("[a-c~~a&&b]", [false, true, false, false, false, false]),
+    ("[a-z&&b-y--c]", [false, true, true, false, false, false]),
+    (
+        "[[a-b][y-z]&&[b-y]]",
+        [false, true, false, false, false, false],
+    ),
+];
+
+#[test]
+fn every_front_end() {
+    common::check_extended(&HAYSTACKS, &MATRIX);
+}
+
+#[test]
+fn invalid_posix_classes() {
+    let cases = [
+        (
+            "[[:foo:]]",
+            ErrorKind::UnknownPosixClass("foo".to_string()),
+            1..8,
+        ),
+        ("[[:alpha", ErrorKind::UnterminatedPosixClass, 1..8),
+        ("[[:alpha:", ErrorKind::UnterminatedPosixClass, 1..8),
+    ];
+    for (pattern, kind, span) in cases {
+        let error = infix_to_nfa(pattern).err().unwrap();
+        assert_eq!(error.kind(), &kind, "{}", pattern);
+        assert_eq!(error.char_span(), span, "{}", pattern);
+    }
+}
@@ -0,0 +1,85 @@
+//! Unicode properties, `\p{name}` and `\P{name}`.
+
+use regex::error::ErrorKind;
+use regex::regex1::nfa::{infix_to_nfa, recognize};
+
+fn is_match(pattern: &str, haystack: &str) -> bool {
+    recognize(&infix_to_nfa(pattern).unwrap(), haystack)
+}
+
+#[test]
+fn general_categories() {
+    for haystack in ["7", "٣", "Ⅻ", "½"] {
+        assert!(is_match("\\pN", haystack));
+        assert!(is_match("\\p{Number}", haystack));
+        assert!(!is_match("\\PN", haystack));
+    }
+    assert!(!is_match("\\p{N}", "a"));
+    assert!(is_match("\\p{Nd}", "٣"));
+    assert!(!is_match("\\p{Decimal_Number}", "½"));
+    assert!(is_match("\\p{Cc}", "\u{7}"));
+    assert!(!is_match("\\p{cc}", "\u{AD}"));
+    assert!(is_match("[\\p{N}x]+", "x1½"));
+}
+
+#[test]
+fn binary_properties() {
+    assert!(is_match("\\p{Alphabetic}", "\u{903}"));
+    assert!(is_match("\\p{alpha}", "é"));
+    assert!(is_match("\\p{Uppercase}", "\u{2160}"));
+    assert!(!is_match("\\p{Uppercase}", "a"));
+    assert!(is_match("\\p{Lowercase}", "ª"));
+    assert!(is_match("\\p{White_Space}", "\u{2003}"));
+    assert!(is_match("\\P{whitespace}", "a"));
+}
+
+#[test]
+fn letter_categories() {
+    let cases = [
+        ("é", [true, false, true, false]),
+        ("Σ", [true, true, false, false]),
+        ("ǅ", [true, false, false, true]),
+        ("ʰ", [true, false, false, false]),
+        ("中", [true, false, false, false]),
+        ("ª", [true, false, false, false]),
+        ("\u{2160}", [false, false, false, false]),
+        ("1", [false, false, false, false]),
+    ];
+    for (haystack, expected) in cases {
+        for (pattern, expected) in ["\\pL", "\\p{Lu}", "\\p{Ll}", "\\p{Lt}"]
+            .iter()
+            .zip(expected)
+        {
+            assert_eq!(
+                is_match(pattern, haystack),
+                expected,
+                "{} on {:?}",
+                pattern,
+                haystack
+            );
+        }
+    }
+    assert!(is_match("\\p{Letter}", "ʰ"));
+    assert!(is_match("\\p{lm}", "ʰ"));
+    assert!(is_match("\\p{Other_Letter}", "中"));
+    assert!(is_match("\\p{lowercase_letter}", "é"));
+    assert!(is_match("\\PL", "1"));
+    assert!(!is_match("\\PL", "é"));
+}
+
+#[test]
+fn unknown_properties() {
+    let cases = [
+        (
+            "\\p{Greek}",
+            ErrorKind::UnknownProperty("Greek".to_string()),
+            0..9,
+        ),
+        ("x\\p{Greek", ErrorKind::UnterminatedProperty, 1..9),
+    ];
+    for (pattern, kind, span) in cases {
+        let error = infix_to_nfa(pattern).err().unwrap();
+        assert_eq!(error.kind(), &kind, "{}", pattern);
+        assert_eq!(error.char_span(), span, "{}", pattern);
+    }
+}
@@ -0,0 +1,106 @@
+//! The parser of `regex1` goes on after the errors it can recover from, and
+//! reports all of them at once.
+
+use regex::error::ErrorKind;
+use regex::regex1::parser::Parser;
+
+// The kind, char span and help of every error in `pattern`.
+fn errors(pattern: &str) -> Vec<(ErrorKind, std::ops::Range<usize>, Option<String>)> {
+    Parser::new(pattern.to_string())
+        .parse_all()
+        .err()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|error| {
+            let help = error.help().map(str::to_string);
+            (error.kind().clone(), error.char_span(), help)
+        })
+        .collect()
+}
+
+#[test]
+fn every_error_is_reported() {
+    assert_eq!(
+        errors("a)|+b\\q[z-a](c"),
+        vec![
+            (
+                ErrorKind::UnbalancedParenthesis,
+                1..2,
+                Some("did you mean to escape `)`? write `\\)`".to_string())
+            ),
+            (
+                ErrorKind::MissingOperand,
+                3..4,
+                Some("did you mean to escape `+`? write `\\+`".to_string())
+            ),
+            (
+                ErrorKind::UnknownEscape('q'),
+                5..7,
+                Some("remove the backslash to match `q`".to_string())
+            ),
+            (
+                ErrorKind::InvalidClassRange,
+                8..11,
+                Some("did you mean `a-z`?".to_string())
+            ),
+            (
+                ErrorKind::UnbalancedParenthesis,
+                12..13,
+                Some("close the group with `)`, or write `\\(` to match `(`".to_string())
+            ),
+        ]
+    );
+}
+
+#[test]
+fn errors_are_ordered_by_position() {
+    let kinds: Vec<ErrorKind> = errors("(\\9|*")
+        .into_iter()
+        .map(|(kind, _, _)| kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ErrorKind::UnbalancedParenthesis,
+            ErrorKind::UndefinedBackreference("\\9".to_string()),
+            ErrorKind::MissingOperand,
+        ]
+    );
+}
+
+#[test]
+fn backreferences_in_classes() {
+    assert_eq!(
+        errors("[\\1]x[a\\k<n>]"),
+        vec![
+            (ErrorKind::BackreferenceInClass('1'), 1..3, None),
+            (ErrorKind::BackreferenceInClass('k'), 7..9, None),
+        ]
+    );
+
+    let error = regex::regex1::backtrack::compile("(a)[\\1]").err().unwrap();
+    assert_eq!(error.kind(), &ErrorKind::BackreferenceInClass('1'));
+    assert_eq!(error.char_span(), 4..6);
+}
+
+#[test]
+fn unrecoverable_errors_end_the_parse() {
+    let found = errors("+[a");
+    assert_eq!(found.len(), 2);
+    assert_eq!(found[1].0, ErrorKind::UnterminatedClass);
+}
+
+#[test]
+fn parse_returns_the_first_error() {
+    let error = Parser::new("a{2}{3}(".to_string()).parse().err().unwrap();
+    assert_eq!(error.kind(), &ErrorKind::MissingOperand);
+    assert_eq!(error.char_span(), 4..7);
+    assert!(error
+        .to_string()
+        .ends_with("help: did you mean to escape `{`? write `\\{`"));
+}
+
+#[test]
+fn valid_patterns_have_no_errors() {
+    assert!(errors("(a|b)*c{2}[a-z\\-]\\+").is_empty());
+}
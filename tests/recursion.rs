@@ -0,0 +1,88 @@
+//! Patterns calling groups recursively, matched by the pda.
+
+use regex::error::ErrorKind;
+use regex::pda;
+use regex::regex1::backtrack;
+use regex::regex1::nfa::infix_to_nfa;
+
+fn is_match(pattern: &str, haystack: &str) -> bool {
+    pda::is_match(&pda::compile(pattern).unwrap(), haystack)
+}
+
+#[test]
+fn nested_structures() {
+    let balanced = r"^(\((?1)*\))*$";
+    assert!(is_match(balanced, ""));
+    assert!(is_match(balanced, "(()())()"));
+    assert!(!is_match(balanced, "(()"));
+    assert!(!is_match(balanced, "())("));
+
+    let palindrome = "^(a(?1)a|b(?1)b|a|b|)$";
+    assert!(is_match(palindrome, "abbaabba"));
+    assert!(is_match(palindrome, "ababa"));
+    assert!(!is_match(palindrome, "abab"));
+}
+
+#[test]
+fn left_recursion() {
+    assert!(is_match("^((?1)a|b)$", "baaa"));
+    assert!(!is_match("^((?1)a|b)$", "aaa"));
+    assert!(is_match("^(?R)?a$", "a"));
+}
+
+// Paths through the alternatives of a recursive group multiply with the depth
+// of the recursion, while the calls made at a given position do not.
+#[test]
+fn shared_stacks() {
+    let pattern = "^(a(?1)?b|a(?1)?c)$";
+    let n = 40;
+    let b = format!("{}{}", "a".repeat(n), "b".repeat(n));
+    let mixed = format!(
+        "{}{}c{}",
+        "a".repeat(n),
+        "b".repeat(n / 2),
+        "b".repeat(n / 2 - 1)
+    );
+    assert!(is_match(pattern, &b));
+    assert!(is_match(pattern, &mixed));
+    assert!(!is_match(pattern, &b[1..]));
+    assert!(!is_match(pattern, &format!("{}b", b)));
+}
+
+// Matching the whole haystack takes no anchors, which `(?R)` would call along
+// with the rest of the pattern.
+#[test]
+fn full_matches() {
+    let is_full_match = |pattern: &str, haystack: &str| {
+        pda::is_full_match(&pda::compile(pattern).unwrap(), haystack)
+    };
+    assert!(is_full_match("a(?R)?b", "aaabbb"));
+    assert!(!is_full_match("a(?R)?b", "aabbb"));
+    assert!(is_match("a(?R)?b", "aabbb"));
+    assert!(!is_full_match("^a(?R)?b$", "aabb"));
+    assert!(is_full_match(r"\((?R)*\)", "(()(()))"));
+    assert!(!is_full_match(r"\((?R)*\)", "(()(())"));
+}
+
+#[test]
+fn unsupported_patterns() {
+    let error = pda::compile("(?2)(a)").err().unwrap();
+    assert_eq!(error.kind(), &ErrorKind::UndefinedCall("2".to_string()));
+    assert_eq!(error.char_span(), 0..4);
+    for pattern in ["a(?>b)", "(a)\\1"] {
+        let error = pda::compile(pattern).err().unwrap();
+        assert!(
+            matches!(error.kind(), ErrorKind::Unsupported(_)),
+            "{}",
+            pattern
+        );
+    }
+
+    for error in [
+        infix_to_nfa("(a(?1)?b)").err().unwrap(),
+        backtrack::compile("(a(?1)?b)").err().unwrap(),
+    ] {
+        assert!(matches!(error.kind(), ErrorKind::Unsupported(_)));
+        assert_eq!(error.char_span(), 2..6);
+    }
+}
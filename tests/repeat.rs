@@ -0,0 +1,108 @@
+//! Counted repetitions, which every engine expands into copies of their
+//! operand.
+
+mod common;
+
+use regex::error::ErrorKind;
+use regex::regex1::nfa::{infix_to_nfa, recognize};
+use regex::{pda, regex0, regex1};
+
+const HAYSTACKS: [&str; 6] = ["", "a", "aa", "aaa", "aaaa", "abab"];
+
+const MATRIX: [(&str, [bool; 6]); 11] = [
+    ("a{2}", [false, false, true, false, false, false]),
+    ("a{2,}", [false, false, true, true, true, false]),
+    ("a{1,3}", [false, true, true, true, false, false]),
+    ("a{0}", [true, false, false, false, false, false]),
+    ("a{0,1}", [true, true, false, false, false, false]),
+    ("(a{2}){2}", [false, false, false, false, true, false]),
+    ("(ab){2}", [false, false, false, false, false, true]),
+    ("(?:a|ab){2,3}", [false, false, true, true, false, true]),
+    ("a{1,2}a{2}", [false, false, false, true, true, false]),
+    ("a{2}?", [false, false, true, false, false, false]),
+    ("(a{1,2}){2}", [false, false, true, true, true, false]),
+];
+
+#[test]
+fn every_front_end() {
+    common::check_extended(&HAYSTACKS, &MATRIX);
+}
+
+// A `{` that does not start a counted repetition is a literal.
+#[test]
+fn literal_braces() {
+    common::check_extended(
+        &["a{", "a{x}", "a{,3}", "a{1,2,3}", "{}"],
+        &[
+            ("a{", [true, false, false, false, false]),
+            ("a{x}", [false, true, false, false, false]),
+            ("a{,3}", [false, false, true, false, false]),
+            ("a{1,2,3}", [false, false, false, true, false]),
+            ("{}", [false, false, false, false, true]),
+        ],
+    );
+}
+
+#[test]
+fn invalid_repetitions() {
+    let error = regex1::create_matcher("a{5,2}").err().unwrap();
+    assert_eq!(error.kind(), &ErrorKind::InvalidRepeatRange(5, 2));
+    assert_eq!(error.char_span(), 1..6);
+
+    let error = infix_to_nfa("x(a){1001}").err().unwrap();
+    assert_eq!(error.kind(), &ErrorKind::RepeatTooLarge(1001));
+    assert_eq!(error.char_span(), 4..10);
+
+    let error = regex0::regex::compile("a{2,1001}".to_string())
+        .err()
+        .unwrap();
+    assert_eq!(error.kind(), &ErrorKind::RepeatTooLarge(1001));
+}
+
+#[test]
+fn nested_expansion_is_bounded() {
+    for pattern in [
+        "((a{1000}){1000}){1000}",
+        "(a{1000}){101}",
+        "((ab){500,}){100}",
+    ] {
+        let error = regex1::create_matcher(pattern).err().unwrap();
+        assert_eq!(error.kind(), &ErrorKind::ExpansionTooLarge, "{}", pattern);
+
+        let kind = |error: regex::error::Error| error.kind().clone();
+        assert_eq!(
+            infix_to_nfa(pattern).err().map(kind),
+            Some(ErrorKind::ExpansionTooLarge)
+        );
+        assert_eq!(
+            regex1::backtrack::compile(pattern).err().map(kind),
+            Some(ErrorKind::ExpansionTooLarge)
+        );
+        assert_eq!(
+            regex0::regex::compile(pattern.to_string()).err().map(kind),
+            Some(ErrorKind::ExpansionTooLarge)
+        );
+        assert_eq!(
+            pda::compile(pattern).err().map(kind),
+            Some(ErrorKind::ExpansionTooLarge)
+        );
+    }
+
+    let error = regex1::create_matcher("x((a{10}){100}){1000}y")
+        .err()
+        .unwrap();
+    assert_eq!(error.char_span(), 1..21);
+}
+
+#[test]
+fn expansion_up_to_the_limit() {
+    let haystack = "a".repeat(1000);
+    assert!(recognize(
+        &regex1::create_matcher("(a{10}){100}").unwrap(),
+        &haystack
+    ));
+    assert!(regex0::regex::compile("(a{10}){100}".to_string())
+        .unwrap()
+        .match_regex(&haystack));
+    assert!(regex1::create_matcher("(a{100}){1000}").is_ok());
+}
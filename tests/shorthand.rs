@@ -0,0 +1,95 @@
+//! The shorthand classes `\d`, `\w`, `\s` and their negations, ASCII by
+//! default and Unicode-aware in `u` mode.
+
+mod common;
+
+use regex::flags::Flags;
+use regex::regex0;
+use regex::regex1::nfa::{infix_to_nfa_with_flags, recognize};
+
+const HAYSTACKS: [&str; 8] = ["7", "٣", "a", "é", "_", " ", "\u{2003}", "-"];
+
+const MATRIX: [(&str, [bool; 8]); 16] = [
+    (
+        "\\d",
+        [true, false, false, false, false, false, false, false],
+    ),
+    ("\\D", [false, true, true, true, true, true, true, true]),
+    ("\\w", [true, false, true, false, true, false, false, false]),
+    ("\\W", [false, true, false, true, false, true, true, true]),
+    (
+        "\\s",
+        [false, false, false, false, false, true, false, false],
+    ),
+    ("\\S", [true, true, true, true, true, false, true, true]),
+    (
+        "(?u)\\d",
+        [true, true, false, false, false, false, false, false],
+    ),
+    (
+        "(?u)\\D",
+        [false, false, true, true, true, true, true, true],
+    ),
+    (
+        "(?u)\\w",
+        [true, true, true, true, true, false, false, false],
+    ),
+    (
+        "(?u)\\W",
+        [false, false, false, false, false, true, true, true],
+    ),
+    (
+        "(?u)\\s",
+        [false, false, false, false, false, true, true, false],
+    ),
+    (
+        "(?u)\\S",
+        [true, true, true, true, true, false, false, true],
+    ),
+    (
+        "(?u:\\s)",
+        [false, false, false, false, false, true, true, false],
+    ),
+    (
+        "(?u)(?-u:\\w)",
+        [true, false, true, false, true, false, false, false],
+    ),
+    (
+        "[\\d_]",
+        [true, false, false, false, true, false, false, false],
+    ),
+    (
+        "[^\\W\\d]",
+        [false, false, true, false, true, false, false, false],
+    ),
+];
+
+#[test]
+fn every_front_end() {
+    common::check_extended(&HAYSTACKS, &MATRIX);
+}
+
+// Only decimal digits, not every numeric character.
+#[test]
+fn unicode_digits() {
+    common::check_extended(
+        &["7", "٣", "½", "²", "Ⅻ"],
+        &[
+            ("(?u)\\d", [true, true, false, false, false]),
+            ("(?u)\\D", [false, false, true, true, true]),
+        ],
+    );
+}
+
+#[test]
+fn unicode_flag() {
+    let flags = Flags {
+        unicode: true,
+        ..Flags::new()
+    };
+    let nfa = infix_to_nfa_with_flags("\\d+-\\w+", flags).unwrap();
+    assert!(recognize(&nfa, "٣٤-héllo"));
+    let mut nfa = regex0::regex::compile_with_flags("\\d+-\\w+".to_string(), flags).unwrap();
+    assert!(nfa.match_regex("٣٤-héllo"));
+    assert!(!nfa.match_regex("٣٤ héllo"));
+}
@@ -0,0 +1,59 @@
+//! Patterns with multi-byte characters are scanned by char in every front end.
+
+mod common;
+
+use regex::error::ErrorKind;
+use regex::regex1::nfa::{find, infix_to_nfa, recognize};
+use regex::regex1::parser::Parser;
+use regex::{regex0, regex2};
+
+const HAYSTACKS: [&str; 7] = ["é", "ü", "éé", "héllo", "日本語", "a·b", "ε"];
+
+const MATRIX: [(&str, [bool; 7]); 9] = [
+    ("é", [true, false, false, false, false, false, false]),
+    ("é|ü", [true, true, false, false, false, false, false]),
+    ("(é)+", [true, false, true, false, false, false, false]),
+    ("ü?é*", [true, true, true, false, false, false, false]),
+    (
+        "h\\x{e9}llo",
+        [false, false, false, true, false, false, false],
+    ),
+    ("日本語", [false, false, false, false, true, false, false]),
+    (
+        "日(本|x)語?",
+        [false, false, false, false, true, false, false],
+    ),
+    // Chars that once stood for operators in the postfix forms of patterns.
+    ("a·b", [false, false, false, false, false, true, false]),
+    ("ε", [false, false, false, false, false, false, true]),
+];
+
+#[test]
+fn every_front_end() {
+    common::check(&HAYSTACKS, &MATRIX);
+}
+
+#[test]
+fn classes_and_comments() {
+    let nfa = infix_to_nfa("(?#ß)[à-ÿ]+").unwrap();
+    assert_eq!(find(&nfa, "xéü"), Some((1, 5)));
+    assert!(!recognize(&nfa, "ß"));
+    assert!(recognize(&infix_to_nfa("[^ü]").unwrap(), "日"));
+}
+
+#[test]
+fn error_spans() {
+    let error = Parser::new("日本)".to_string()).parse().err().unwrap();
+    assert_eq!(error.kind(), &ErrorKind::UnbalancedParenthesis);
+    assert_eq!(error.char_span(), 2..3);
+    assert_eq!(error.span(), 6..7);
+
+    let error = regex0::regex::compile("é\\q".to_string()).err().unwrap();
+    assert_eq!(error.kind(), &ErrorKind::UnknownEscape('q'));
+    assert_eq!(error.char_span(), 1..3);
+    assert_eq!(error.span(), 2..4);
+
+    let error = regex2::compile("ü*)").err().unwrap();
+    assert_eq!(error.char_span(), 2..3);
+    assert_eq!(error.span(), 3..4);
+}
@@ -0,0 +1,76 @@
+//! Verbose mode, where `x` makes whitespace and `#` comments insignificant,
+//! and inline `(?#...)` comments, which work in every mode.
+
+mod common;
+
+use regex::error::ErrorKind;
+use regex::flags::Flags;
+use regex::regex0;
+use regex::regex1::nfa::infix_to_nfa;
+
+const HAYSTACKS: [&str; 6] = ["abc", "a bc", "ab c", "a#bc", "a", "aaa"];
+
+const MATRIX: [(&str, [bool; 6]); 12] = [
+    ("(?x) a b c ", [true, false, false, false, false, false]),
+    (
+        "(?x)\ta\n\tb\r\n\tc",
+        [true, false, false, false, false, false],
+    ),
+    (
+        "(?x)a # the first letter\nbc",
+        [true, false, false, false, false, false],
+    ),
+    (
+        "(?x)a # the rest of the line is ignored bc\n",
+        [false, false, false, false, true, false],
+    ),
+    ("(?x)a\\ bc", [false, true, false, false, false, false]),
+    ("(?x)a\\#bc", [false, false, false, true, false, false]),
+    // Whitespace and `#` are kept inside classes.
+    ("(?x)a[ ]bc", [false, true, false, false, false, false]),
+    ("(?x)a[#]bc", [false, false, false, true, false, false]),
+    ("(?x)a b (?-x) c", [false, false, true, false, false, false]),
+    ("(?x: a b ) c", [false, false, true, false, false, false]),
+    (
+        "a(?#the first letter)bc",
+        [true, false, false, false, false, false],
+    ),
+    (
+        "a(?#one or more)+",
+        [false, false, false, false, true, true],
+    ),
+];
+
+#[test]
+fn every_front_end() {
+    common::check_extended(&HAYSTACKS, &MATRIX);
+}
+
+#[test]
+fn whitespace_is_literal_without_x() {
+    common::check_extended(
+        &["a b", "ab"],
+        &[("a b", [true, false]), ("a#b", [false, false])],
+    );
+}
+
+#[test]
+fn verbose_flag() {
+    let flags = Flags {
+        verbose: true,
+        ..Flags::new()
+    };
+    let mut nfa = regex0::regex::compile_with_flags(" a b # c\n c".to_string(), flags).unwrap();
+    assert!(nfa.match_regex("abc"));
+    assert!(!nfa.match_regex("a bc"));
+}
+
+#[test]
+fn unterminated_comments() {
+    let cases = [("a(?#b", 1..5), ("(?#", 0..3)];
+    for (pattern, span) in cases {
+        let error = infix_to_nfa(pattern).err().unwrap();
+        assert_eq!(error.kind(), &ErrorKind::UnterminatedComment, "{}", pattern);
+        assert_eq!(error.char_span(), span, "{}", pattern);
+    }
+}